@@ -0,0 +1,19 @@
+use k_board::{
+    evdev::{Device, KeyState},
+    keys::Keys,
+};
+
+fn main() -> std::io::Result<()> {
+    let device = match std::env::args().nth(1) {
+        Some(path) => Device::open(path)?,
+        None => Device::keyboard()?,
+    };
+    for event in device {
+        let event = event?;
+        println!("{:?} {:?} (code {})", event.state, event.key, event.code);
+        if event.key == Some(Keys::Escape) && event.state == KeyState::Up {
+            break;
+        }
+    }
+    Ok(())
+}
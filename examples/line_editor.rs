@@ -0,0 +1,17 @@
+use k_board::{
+    completion::Words,
+    line_editor::{LineEditor, ReadLine},
+};
+
+fn main() -> std::io::Result<()> {
+    let mut editor =
+        LineEditor::new().with_completer(Words::new(["exit", "help", "hello", "history"]));
+    loop {
+        match editor.read_line("> ")? {
+            ReadLine::Line(line) if line == "exit" => break,
+            ReadLine::Line(line) => println!("you wrote: {}", line),
+            ReadLine::Cancelled => break,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use k_board::password::read_password;
+
+fn main() -> std::io::Result<()> {
+    let password = read_password("Password: ", Some('*'))?;
+    println!("{:?} has {} bytes", password, password.len());
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use k_board::picker::Picker;
+
+fn main() -> std::io::Result<()> {
+    if let Some(picked) = Picker::from_stdin()?.multi(true).read()? {
+        for item in picked {
+            println!("{}", item);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use k_board::prompt::{Confirm, MultiSelect, Select};
+
+fn main() -> std::io::Result<()> {
+    let languages = ["Rust", "C", "Go", "Zig", "Haskell", "OCaml", "Python"];
+    let favorite = Select::new("Favorite language?", languages).read()?;
+    let known = MultiSelect::new("Which do you know?", languages)
+        .page_size(4)
+        .filter(true)
+        .read()?;
+    if Confirm::new("Save the answers?").default(true).read()? == Some(true) {
+        println!("favorite: {:?}, known: {:?}", favorite, known);
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use k_board::{keyboard::Keyboard, keys::Keys};
+use k_board::{keyboard::Keyboard, keys::Keys, output::Output};
 
 fn main() {
     menu(0);
@@ -14,14 +14,17 @@ fn main() {
 }
 
 fn menu(operation: u8) {
-    std::process::Command::new("clear").status().unwrap();
     let mut op: Vec<char> = vec!['*', ' '];
     if operation == 1 {
         op[0] = ' ';
         op[1] = '*';
     }
-    println!(
-        "[{}] I use k_board lightweight software\n[{}] I use heavyweight software",
-        op[0], op[1]
-    );
+    Output::new()
+        .clear_screen()
+        .text(&format!(
+            "[{}] I use k_board lightweight software\n[{}] I use heavyweight software\n",
+            op[0], op[1]
+        ))
+        .flush()
+        .unwrap();
 }
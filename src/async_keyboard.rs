@@ -0,0 +1,176 @@
+/***************************************************************************************
+ *   async_keyboard.rs  --  This file is part of k_board.                              *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    event::KeyEvent,
+    keyboard::Keyboard,
+    keys::Keys,
+    termio::{fcntl, raw_mode_fd, RawMode, F_GETFL, F_SETFL, O_NONBLOCK},
+};
+use futures_core::Stream;
+use std::{
+    ffi::c_int,
+    io::{Error, ErrorKind, Result},
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// Fd of the keyboard, registered with the reactor without owning it
+struct Terminal(c_int);
+
+impl AsRawFd for Terminal {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl AsFd for Terminal {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// Reactor waking the stream up when the terminal is readable
+enum Reactor {
+    #[cfg(feature = "tokio")]
+    Tokio(tokio::io::unix::AsyncFd<Terminal>),
+    #[cfg(feature = "async-std")]
+    AsyncStd(async_io::Async<Terminal>),
+}
+
+impl Reactor {
+    /// Register `fd` with the tokio runtime running, else with the
+    /// async-io reactor that async-std uses
+    fn register(fd: c_int) -> Result<Reactor> {
+        #[cfg(feature = "tokio")]
+        if tokio::runtime::Handle::try_current().is_ok() {
+            let interest = tokio::io::Interest::READABLE;
+            // The keyboard keeps the fd open until the reactor is dropped,
+            // that is the first field of `AsyncKeyboard`
+            let registered =
+                unsafe { tokio::io::unix::AsyncFd::register_with_interest(Terminal(fd), interest) };
+            return registered.map(Reactor::Tokio).map_err(Error::from);
+        }
+        #[cfg(feature = "async-std")]
+        {
+            async_io::Async::new_nonblocking(Terminal(fd)).map(Reactor::AsyncStd)
+        }
+        #[cfg(not(feature = "async-std"))]
+        {
+            Err(Error::other("AsyncKeyboard needs a tokio runtime"))
+        }
+    }
+
+    /// `Keyboard::fill` once the terminal is readable
+    fn poll_fill(&self, keyboard: &mut Keyboard, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        loop {
+            match self {
+                #[cfg(feature = "tokio")]
+                Reactor::Tokio(fd) => {
+                    let mut guard = ready!(fd.poll_read_ready(cx))?;
+                    match keyboard.fill() {
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => guard.clear_ready(),
+                        read => return Poll::Ready(read),
+                    }
+                }
+                #[cfg(feature = "async-std")]
+                Reactor::AsyncStd(fd) => match keyboard.fill() {
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                        ready!(fd.poll_readable(cx))?
+                    }
+                    read => return Poll::Ready(read),
+                },
+            }
+        }
+    }
+}
+
+/// Keyboard read as a `Stream` of key events (never `Keys::Null`), to
+/// `select!` keys along timers & sockets. The terminal stays in raw mode
+/// until it is dropped
+pub struct AsyncKeyboard {
+    reactor: Reactor,
+    _raw_mode: RawMode,
+    keyboard: Keyboard,
+    /// Status flags of the fd before `O_NONBLOCK`
+    flags: c_int,
+}
+
+impl AsyncKeyboard {
+    /// Put the terminal of `keyboard` in raw mode & register it with the
+    /// reactor of the tokio runtime running, else with the async-std one.
+    /// Ctrl + C arrives as a key instead of a signal, like in `Keyboard::spawn`.
+    /// The fd is made non-blocking, so prefer `Keyboard::from_tty` if stdout
+    /// is the same terminal as stdin
+    pub fn new(keyboard: Keyboard) -> Result<Self> {
+        let fd = keyboard.fd();
+        let raw_mode = raw_mode_fd(fd, false, &[])?;
+        let flags = unsafe { fcntl(fd, F_GETFL) };
+        if flags < 0 || unsafe { fcntl(fd, F_SETFL, flags | O_NONBLOCK) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        let reactor = match Reactor::register(fd) {
+            Ok(reactor) => reactor,
+            Err(err) => {
+                unsafe { fcntl(fd, F_SETFL, flags) };
+                return Err(err);
+            }
+        };
+        let mut async_keyboard = AsyncKeyboard {
+            reactor,
+            _raw_mode: raw_mode,
+            keyboard,
+            flags,
+        };
+        async_keyboard.keyboard.set_modify_other_keys(true)?;
+        Ok(async_keyboard)
+    }
+}
+
+impl Stream for AsyncKeyboard {
+    type Item = Result<KeyEvent>;
+
+    /// Next key event decoded, reading the terminal once it is readable.
+    /// The stream ends with the input
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<KeyEvent>>> {
+        let this = self.get_mut();
+        loop {
+            while let Some(event) = this.keyboard.pending_event() {
+                if event.key != Keys::Null {
+                    return Poll::Ready(Some(Ok(event)));
+                }
+            }
+            match ready!(this.reactor.poll_fill(&mut this.keyboard, cx)) {
+                Ok(0) => return Poll::Ready(None),
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
+
+impl Drop for AsyncKeyboard {
+    fn drop(&mut self) {
+        unsafe { fcntl(self.keyboard.fd(), F_SETFL, self.flags) };
+        let _ = self.keyboard.set_modify_other_keys(false);
+    }
+}
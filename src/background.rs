@@ -0,0 +1,166 @@
+/***************************************************************************************
+ *   background.rs  --  This file is part of k_board.                                  *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    event::KeyEvent,
+    keyboard::Keyboard,
+    keys::Keys,
+    termio::{
+        close, pipe2, poll, pollfd, restore_fd, setup_raw_mode_fd, termios, write, O_CLOEXEC,
+        POLLERR, POLLHUP, POLLIN, POLLNVAL,
+    },
+};
+use std::{
+    ffi::c_int,
+    io::{Error, ErrorKind, Result},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Keyboard read by a background thread, see `Keyboard::spawn`
+pub struct KeyboardHandle {
+    events: Receiver<KeyEvent>,
+    /// Write end of the pipe that wakes the thread up to stop it
+    stop: c_int,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Keyboard {
+    /// Move the keyboard to a thread that keeps the terminal in raw mode &
+    /// sends each key event (never `Keys::Null`) to `KeyboardHandle::events`.
+    /// Ctrl + C arrives as a key instead of a signal, so there is no race on
+    /// the `CTRL_C` flag or the termios. Stopping or dropping the handle wakes
+    /// the thread up, which restores the terminal & ends
+    pub fn spawn(self) -> Result<KeyboardHandle> {
+        // close on exec, so a child of the program can't keep the stop pipe open
+        let mut fds: [c_int; 2] = [0; 2];
+        if unsafe { pipe2(fds.as_mut_ptr(), O_CLOEXEC) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        let (sender, events) = channel();
+        let thread = thread::Builder::new()
+            .name(String::from("k_board"))
+            .spawn(move || {
+                let read = run(self, fds[0], sender);
+                unsafe { close(fds[0]) };
+                read
+            });
+        match thread {
+            Ok(thread) => Ok(KeyboardHandle {
+                events,
+                stop: fds[1],
+                thread: Some(thread),
+            }),
+            Err(err) => {
+                unsafe {
+                    close(fds[0]);
+                    close(fds[1]);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+impl KeyboardHandle {
+    /// Key events in the order they arrived. It disconnects once the
+    /// thread ends, on `stop`, a read error or the end of the input
+    pub fn events(&self) -> &Receiver<KeyEvent> {
+        &self.events
+    }
+
+    /// Stop the thread & wait until it restored the terminal, returning
+    /// the read error that ended it, if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        unsafe { write(self.stop, b"x".as_ptr().cast(), 1) };
+        let read = thread
+            .join()
+            .unwrap_or_else(|_| Err(Error::other("keyboard thread panicked")));
+        unsafe { close(self.stop) };
+        read
+    }
+}
+
+impl Drop for KeyboardHandle {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// Body of the thread: raw mode without signals around the reads. The read
+/// error wins over a restore one, as it's the cause
+fn run(mut keyboard: Keyboard, stop: c_int, sender: Sender<KeyEvent>) -> Result<()> {
+    let fd = keyboard.fd();
+    let termios_enviroment: termios = setup_raw_mode_fd(fd, false)?;
+    let read = keyboard
+        .set_modify_other_keys(true)
+        .and_then(|_| read_events(&mut keyboard, stop, &sender));
+    let _ = keyboard.set_modify_other_keys(false);
+    let restore = restore_fd(fd, &termios_enviroment);
+    read.and(restore)
+}
+
+/// Wait on the terminal & the stop pipe, sending the events decoded until the
+/// pipe is written, the input ends or nobody receives them
+fn read_events(keyboard: &mut Keyboard, stop: c_int, sender: &Sender<KeyEvent>) -> Result<()> {
+    loop {
+        while let Some(event) = keyboard.pending_event() {
+            if event.key != Keys::Null && sender.send(event).is_err() {
+                return Ok(());
+            }
+        }
+        let mut fds = [
+            pollfd {
+                fd: keyboard.fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: stop,
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        if unsafe { poll(fds.as_mut_ptr(), 2, -1) } < 0 {
+            let err = Error::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if fds[1].revents != 0 {
+            return Ok(());
+        }
+        if fds[0].revents & POLLIN != 0 {
+            if keyboard.fill()? == 0 {
+                return Ok(());
+            }
+        } else if fds[0].revents & (POLLERR | POLLHUP | POLLNVAL) != 0 {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,191 @@
+/***************************************************************************************
+ *   completion.rs  --  This file is part of k_board.                                  *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use std::{fs, path::Path};
+
+/// Source of candidates for Tab in the `LineEditor`
+pub trait Completer {
+    /// Complete the word before `pos`, a byte index in `line`. Gives the byte
+    /// index where the word starts & the texts that can replace it
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>);
+}
+
+impl<F: Fn(&str, usize) -> (usize, Vec<String>)> Completer for F {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        self(line, pos)
+    }
+}
+
+/// Completes the word before the cursor from a fixed list, like commands
+#[derive(Debug, Clone, Default)]
+pub struct Words {
+    words: Vec<String>,
+}
+
+impl Words {
+    /// Complete from `words`
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(words: I) -> Self {
+        Words {
+            words: words.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Completer for Words {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = word_start(line, pos);
+        let word = &line[start..pos];
+        let candidates = self
+            .words
+            .iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .cloned()
+            .collect();
+        (start, candidates)
+    }
+}
+
+/// Completes the path before the cursor from the files on disk, directories
+/// end with `/`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Paths;
+
+impl Completer for Paths {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = word_start(line, pos);
+        let word = &line[start..pos];
+        let (dir, name) = match word.rfind('/') {
+            Some(slash) => word.split_at(slash + 1),
+            None => ("", word),
+        };
+        let entries = match fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+            Ok(entries) => entries,
+            Err(_) => return (start, Vec::new()),
+        };
+        let mut candidates: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                if !file_name.starts_with(name)
+                    || (file_name.starts_with('.') && !name.starts_with('.'))
+                {
+                    return None;
+                }
+                let slash = match Path::new(dir).join(&file_name).is_dir() {
+                    true => "/",
+                    false => "",
+                };
+                Some(format!("{}{}{}", dir, file_name, slash))
+            })
+            .collect();
+        candidates.sort();
+        (start, candidates)
+    }
+}
+
+/// Byte index after the last whitespace before `pos`
+fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos].rfind(char::is_whitespace).map_or(0, |space| {
+        space + line[space..].chars().next().map_or(1, char::len_utf8)
+    })
+}
+
+/// Longest prefix shared by all the candidates
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, c), _)| index + c.len_utf8())
+            .min(len);
+    }
+    &first[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| String::from(*word)).collect()
+    }
+
+    #[test]
+    fn common_prefixes() {
+        assert_eq!(common_prefix(&[]), "");
+        assert_eq!(common_prefix(&strings(&["cargo"])), "cargo");
+        assert_eq!(common_prefix(&strings(&["cargo", "cat", "cd"])), "c");
+        assert_eq!(common_prefix(&strings(&["build", "bench", "run"])), "");
+        assert_eq!(common_prefix(&strings(&["test", "tests"])), "test");
+        assert_eq!(common_prefix(&strings(&["tests", "test"])), "test");
+        assert_eq!(common_prefix(&strings(&["ñandú", "ñame"])), "ña");
+        assert_eq!(common_prefix(&strings(&["añb", "añc", "añ"])), "añ");
+        assert_eq!(common_prefix(&strings(&["é", "e"])), "");
+    }
+
+    #[test]
+    fn words_before_the_cursor() {
+        let words = Words::new(["build", "bench", "run", "ñu"]);
+        assert_eq!(words.complete("b", 1), (0, strings(&["build", "bench"])));
+        assert_eq!(words.complete("cargo bu", 8), (6, strings(&["build"])));
+        assert_eq!(words.complete("cargo bu x", 8), (6, strings(&["build"])));
+        assert_eq!(
+            words.complete("cargo ", 6),
+            (6, strings(&["build", "bench", "run", "ñu"]))
+        );
+        assert_eq!(words.complete("x\u{3000}ñ", 6), (4, strings(&["ñu"])));
+        assert_eq!(words.complete("cargo x", 7), (6, Vec::new()));
+        let closure = |line: &str, pos: usize| (pos, vec![line.to_uppercase()]);
+        assert_eq!(closure.complete("ab", 2), (2, strings(&["AB"])));
+    }
+
+    #[test]
+    fn paths_on_disk() {
+        let dir = std::env::temp_dir().join(format!("k_board-paths-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("alps")).unwrap();
+        for file in ["alpha.txt", "beta", ".alpine"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let dir_name = format!("{}/", dir.display());
+        let line = format!("open {}al", dir_name);
+        let (start, candidates) = Paths.complete(&line, line.len());
+        assert_eq!(start, 5);
+        let expected = [
+            format!("{}alpha.txt", dir_name),
+            format!("{}alps/", dir_name),
+        ];
+        assert_eq!(candidates, expected);
+        let line = format!("{}.al", dir_name);
+        assert_eq!(
+            Paths.complete(&line, line.len()).1,
+            [format!("{}.alpine", dir_name)]
+        );
+        let line = format!("{}missing/", dir_name);
+        assert_eq!(Paths.complete(&line, line.len()), (0, Vec::new()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+/***************************************************************************************
+ *   config.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keymap::Keymap,
+    keys::{Keys, ParseKeyError},
+};
+use std::{fmt, fs, io, path::Path};
+
+/// Sequence of keys bound to an action name in a config file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Binding {
+    pub sequence: Vec<Keys>,
+    pub action: String,
+    /// Line of the config where the binding is, starting at 1
+    pub line: usize,
+}
+
+/// Error in a config file, `line` & `column` start at 1
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for io::Error {
+    fn from(err: ConfigError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Parse the bindings of a config, one per line. The keys are separated
+/// by spaces or tabs & use the names of `Keys::from_str`. The action goes
+/// after the last `=`, so `=` & `#` can be keys too:
+///
+/// ```text
+/// # comment
+/// ctrl+s = save
+/// alt+shift+left = word_left
+/// f5 = reload
+/// g g = top
+/// ctrl+= = zoom_in
+/// # = hash
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Binding>, ConfigError> {
+    let mut bindings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let content = strip_comment(line);
+        if content.trim().is_empty() {
+            continue;
+        }
+        // the last `=`, as the action has no `=` but a key can, like `ctrl+=`
+        let equal = content.rfind('=').ok_or_else(|| ConfigError {
+            line: index + 1,
+            column: column_of(line, content.trim_start()),
+            message: String::from("expected `keys = action`"),
+        })?;
+        let action = content[equal + 1..].trim();
+        if action.is_empty() || action.contains(char::is_whitespace) {
+            return Err(ConfigError {
+                line: index + 1,
+                column: column_of(line, content[equal + 1..].trim_start()),
+                message: format!("invalid action name `{}`", action),
+            });
+        }
+        let sequence = parse_sequence(&content[..equal], index + 1, 1)?;
+        bindings.push(Binding {
+            sequence,
+            action: String::from(action),
+            line: index + 1,
+        });
+    }
+    Ok(bindings)
+}
+
+/// Parse the bindings of a TOML style config, the keys & the actions are strings
+/// of the `[keys]` table (or at the top of the file):
+///
+/// ```toml
+/// [keys]
+/// "ctrl+s" = "save"
+/// "g g" = "top"
+/// f5 = "reload"
+/// ```
+#[cfg(feature = "toml")]
+pub fn parse_toml(text: &str) -> Result<Vec<Binding>, ConfigError> {
+    let mut bindings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let column = |part: &str| column_of(line, part);
+        let content = strip_comment(line).trim_end();
+        let trimmed = content.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(table) = trimmed.strip_prefix('[') {
+            match table.strip_suffix(']').map(str::trim) {
+                Some("keys") => continue,
+                Some(name) => {
+                    return Err(ConfigError {
+                        line: number,
+                        column: column(table),
+                        message: format!("unknown table `{}`, expected `keys`", name),
+                    })
+                }
+                None => {
+                    return Err(ConfigError {
+                        line: number,
+                        column: column(&trimmed[trimmed.len()..]),
+                        message: String::from("expected `]`"),
+                    })
+                }
+            }
+        }
+        let (keys, rest) = toml_token(trimmed, number, column(trimmed))?;
+        let rest = rest.trim_start();
+        let Some(value) = rest.strip_prefix('=') else {
+            return Err(ConfigError {
+                line: number,
+                column: column(rest),
+                message: String::from("expected `=`"),
+            });
+        };
+        let value = value.trim_start();
+        if !value.starts_with('"') {
+            return Err(ConfigError {
+                line: number,
+                column: column(value),
+                message: String::from("expected a string with the action name"),
+            });
+        }
+        let (action, after) = toml_token(value, number, column(value))?;
+        let after = after.trim_start();
+        if !after.is_empty() {
+            return Err(ConfigError {
+                line: number,
+                column: column(after),
+                message: String::from("unexpected text after the action"),
+            });
+        }
+        let sequence = parse_sequence(keys, number, column(keys))?;
+        bindings.push(Binding {
+            sequence,
+            action: String::from(action),
+            line: number,
+        });
+    }
+    Ok(bindings)
+}
+
+/// Read & parse a config file, see `parse`
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<Binding>> {
+    Ok(parse(&fs::read_to_string(path)?)?)
+}
+
+/// Read & parse a TOML style config file, see `parse_toml`
+#[cfg(feature = "toml")]
+pub fn load_toml(path: impl AsRef<Path>) -> io::Result<Vec<Binding>> {
+    Ok(parse_toml(&fs::read_to_string(path)?)?)
+}
+
+/// Build the keymap of a program from its default config & the bindings
+/// of the user, that replace the default ones on the same keys
+pub fn keymap(default: &[Binding], user: &[Binding]) -> Keymap<String> {
+    let mut keymap = Keymap::new();
+    for binding in default.iter().chain(user) {
+        keymap.bind(&binding.sequence, binding.action.clone());
+    }
+    keymap
+}
+
+/// Parse the keys of a binding separated by spaces or tabs, like `ctrl+x ctrl+s`
+fn parse_sequence(keys: &str, line: usize, column: usize) -> Result<Vec<Keys>, ConfigError> {
+    let mut sequence = Vec::new();
+    for name in keys.split_whitespace() {
+        let key = name.parse().map_err(|err: ParseKeyError| ConfigError {
+            line,
+            column: column + column_of(keys, name) - 1,
+            message: err.to_string(),
+        })?;
+        sequence.push(key);
+    }
+    if sequence.is_empty() {
+        return Err(ConfigError {
+            line,
+            column,
+            message: String::from("expected a key before `=`"),
+        });
+    }
+    Ok(sequence)
+}
+
+/// Take a bare or quoted TOML string at the start of `text`, at `column`,
+/// returning it with the rest of the text
+#[cfg(feature = "toml")]
+fn toml_token(text: &str, line: usize, column: usize) -> Result<(&str, &str), ConfigError> {
+    match text.strip_prefix('"') {
+        Some(quoted) => {
+            let end = quoted.find('"').ok_or_else(|| ConfigError {
+                line,
+                column,
+                message: String::from("unterminated string"),
+            })?;
+            Ok((&quoted[..end], &quoted[end + 1..]))
+        }
+        None => {
+            let end = text
+                .find(|c: char| c.is_whitespace() || c == '=')
+                .unwrap_or(text.len());
+            Ok(text.split_at(end))
+        }
+    }
+}
+
+/// The line without its `#` comment: a line starting with `#`, but for
+/// `# = action` that binds the `#` key, or a `#` after a space past the `=`
+fn strip_comment(line: &str) -> &str {
+    if let Some(rest) = line.trim_start().strip_prefix('#') {
+        let binding = rest
+            .trim_start()
+            .strip_prefix('=')
+            .is_some_and(|action| !action.starts_with('='));
+        if !binding {
+            return &line[..0];
+        }
+    }
+    let Some(equal) = line.find('=') else {
+        return line;
+    };
+    let comment = line[equal..]
+        .match_indices('#')
+        .map(|(start, _)| equal + start)
+        .find(|&start| line[..start].ends_with(char::is_whitespace));
+    match comment {
+        Some(start) => &line[..start],
+        None => line,
+    }
+}
+
+/// Column where `part`, a slice of `line`, starts, counting chars
+fn column_of(line: &str, part: &str) -> usize {
+    line[..part.as_ptr() as usize - line.as_ptr() as usize]
+        .chars()
+        .count()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Line, column & message of the error parsing `text`
+    fn error(text: &str) -> (usize, usize, String) {
+        let err = parse(text).unwrap_err();
+        (err.line, err.column, err.message)
+    }
+
+    fn sequence(text: &str) -> Vec<Keys> {
+        let bindings = parse(text).unwrap();
+        assert_eq!(bindings.len(), 1, "{:?}", text);
+        bindings[0].sequence.clone()
+    }
+
+    #[test]
+    fn keys_with_equal_hash_and_tabs() {
+        assert_eq!(sequence("ctrl+= = zoom_in"), [Keys::Ctrl('=')]);
+        assert_eq!(sequence("= = equal"), [Keys::Char('=')]);
+        assert_eq!(sequence("ctrl+s=save"), [Keys::Ctrl('s')]);
+        assert_eq!(sequence("g\tg = top"), [Keys::Char('g'); 2]);
+        assert_eq!(sequence("  g \t g\t= top # comment"), [Keys::Char('g'); 2]);
+        assert_eq!(sequence("# = hash"), [Keys::Char('#')]);
+        assert_eq!(sequence("#= hash # comment = x"), [Keys::Char('#')]);
+        assert_eq!(
+            sequence("g # = hash_after_g"),
+            [Keys::Char('g'), Keys::Char('#')]
+        );
+        let bindings = parse("# ctrl+s = save\n#=== keys ===\nx = del # = y\n").unwrap();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].action, "del");
+        assert_eq!(bindings[0].line, 3);
+    }
+
+    #[test]
+    fn error_positions() {
+        let text = "ctrl+s = save\n  ctrl+q quit\n";
+        assert_eq!(
+            error(text),
+            (2, 3, String::from("expected `keys = action`"))
+        );
+        let text = "ctrl+s = save\nx =  bad action\n";
+        assert_eq!(
+            error(text),
+            (2, 6, String::from("invalid action name `bad action`"))
+        );
+        assert_eq!(
+            error("x =   # comment"),
+            (1, 7, String::from("invalid action name ``"))
+        );
+        let (line, column, message) = error("\n\ng\tfoo = top");
+        assert_eq!((line, column), (3, 3));
+        assert!(message.contains("foo"), "{}", message);
+        let (line, column, _) = error("ctrl+s = save\n   ctrl+x   nope+s = save");
+        assert_eq!((line, column), (2, 13));
+        assert_eq!(
+            error("  = top"),
+            (1, 1, String::from("expected a key before `=`"))
+        );
+        // columns count chars, not bytes
+        assert_eq!(error("\u{3000}ctrl+q quit").1, 2);
+        assert_eq!(error("ñ =  bad action").1, 6);
+        let (line, column, message) = error("ñ ü\tnope+é = save");
+        assert_eq!((line, column), (1, 5));
+        assert!(message.contains("nope+é"), "{}", message);
+    }
+
+    #[cfg(feature = "toml")]
+    fn toml_error(text: &str) -> (usize, usize) {
+        let err = parse_toml(text).unwrap_err();
+        (err.line, err.column)
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_error_positions() {
+        assert_eq!(toml_error("[other]"), (1, 2));
+        assert_eq!(toml_error("  [keys"), (1, 8));
+        assert_eq!(toml_error("[keys]\n\"g g\" \"top\""), (2, 7));
+        assert_eq!(toml_error("\"g g\" = top"), (1, 9));
+        assert_eq!(toml_error("\"g g\" = \"top\" x"), (1, 15));
+        assert_eq!(toml_error("\"g g\" = \"top"), (1, 9));
+        assert_eq!(toml_error("\"g nope\" = \"top\""), (1, 4));
+        assert_eq!(toml_error("\"ñ nope\" = \"top\""), (1, 4));
+        assert_eq!(toml_error("\"ñ\" = \"top\" x"), (1, 13));
+        assert_eq!(toml_error("ñ = \"top\" \"ü\""), (1, 11));
+        assert_eq!(toml_error("\u{3000}[ñ"), (1, 4));
+        let bindings = parse_toml("[keys]\n\"ctrl+=\" = \"zoom_in\" # comment").unwrap();
+        assert_eq!(bindings[0].sequence, [Keys::Ctrl('=')]);
+    }
+}
@@ -0,0 +1,344 @@
+/***************************************************************************************
+ *   encode.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::keys::{KeyCode, Keys, Modifiers};
+
+/// Terminal whose bytes are generated
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Mode {
+    /// xterm with normal cursor keys (`ESC [ A`)
+    Normal,
+    /// xterm with application cursor keys, DECCKM (`ESC O A`)
+    Application,
+    /// Linux console (`ESC [ [ A` for F1)
+    Linux,
+}
+
+/// Bytes that the terminal sends for `key`, the inverse of the keys tables, so a
+/// decoded key can be written to a child process in a pty. `None` for `Keys::Null`
+/// & the keys the terminal can't send (modifiers on the Linux console, AltGr keys
+/// whose table is not enabled).
+/// Ctrl & Alt keys take the bytes of the enabled tables, the xterm ones otherwise.
+/// Enter is `\r`, the termios of the child turns it into `\n` like it does for the keyboard.
+/// Keys with modifiers that have no legacy form use xterm `modifyOtherKeys`
+/// (`CSI 27 ; mod ; code ~`)
+pub fn encode(key: &Keys, mode: Mode) -> Option<Vec<u8>> {
+    match *key {
+        Keys::Up => Some(cursor(b'A', mode)),
+        Keys::Down => Some(cursor(b'B', mode)),
+        Keys::Right => Some(cursor(b'C', mode)),
+        Keys::Left => Some(cursor(b'D', mode)),
+        Keys::Home if mode == Mode::Linux => Some(b"\x1b[1~".to_vec()),
+        Keys::End if mode == Mode::Linux => Some(b"\x1b[4~".to_vec()),
+        Keys::Home => Some(cursor(b'H', mode)),
+        Keys::End => Some(cursor(b'F', mode)),
+        Keys::Enter => Some(vec![b'\r']),
+        Keys::Tab => Some(vec![b'\t']),
+        Keys::Backtab => Some(b"\x1b[Z".to_vec()),
+        Keys::Space => Some(vec![b' ']),
+        Keys::Delete => Some(vec![0x7F]),
+        Keys::Escape => Some(vec![0x1B]),
+        Keys::Char(c) => Some(utf8(c)),
+        Keys::F(number) => function(number, Modifiers::NONE, mode),
+        Keys::Ctrl(c) => from_tables(*key).or_else(|| ctrl(c).map(|byte| vec![byte])),
+        Keys::Alt(c) => from_tables(*key).or_else(|| {
+            let mut bytes = vec![0x1B];
+            bytes.extend(utf8(c));
+            Some(bytes)
+        }),
+        Keys::AltGr(_) => from_tables(*key),
+        Keys::Modified(modifiers, code) => modified(modifiers, code, mode),
+        Keys::Unknown(raw) => Some(raw.as_bytes().to_vec()),
+        Keys::Null => None,
+    }
+}
+
+/// Cursor key, CSI or SS3 as the mode says
+fn cursor(final_byte: u8, mode: Mode) -> Vec<u8> {
+    match mode {
+        Mode::Application => vec![0x1B, b'O', final_byte],
+        Mode::Normal | Mode::Linux => vec![0x1B, b'[', final_byte],
+    }
+}
+
+/// F1..F12, with the xterm modifier parameter if any
+fn function(number: u8, modifiers: Modifiers, mode: Mode) -> Option<Vec<u8>> {
+    let modified = !modifiers.is_empty();
+    if mode == Mode::Linux && !modified && (1..=5).contains(&number) {
+        return Some(vec![0x1B, b'[', b'[', b'A' + number - 1]);
+    }
+    match number {
+        1..=4 if modified => Some(
+            format!(
+                "\x1b[1;{}{}",
+                modifiers.to_xterm(),
+                (b'P' + number - 1) as char
+            )
+            .into_bytes(),
+        ),
+        1..=4 => Some(vec![0x1B, b'O', b'P' + number - 1]),
+        5..=12 => {
+            let code = [15, 17, 18, 19, 20, 21, 23, 24][number as usize - 5];
+            match modified {
+                true => Some(format!("\x1b[{};{}~", code, modifiers.to_xterm()).into_bytes()),
+                false => Some(format!("\x1b[{}~", code).into_bytes()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Control byte of Ctrl + `c`, like xterm does
+fn ctrl(c: char) -> Option<u8> {
+    match c {
+        'a'..='z' | 'A'..='Z' => Some(c.to_ascii_lowercase() as u8 & 0x1F),
+        '@' | ' ' | '2' => Some(0x00),
+        '[' | '3' => Some(0x1B),
+        '\\' | '4' => Some(0x1C),
+        ']' | '5' => Some(0x1D),
+        '^' | '6' => Some(0x1E),
+        '_' | '-' | '/' | '7' => Some(0x1F),
+        '?' | '8' => Some(0x7F),
+        c if c.is_ascii_graphic() => Some(c as u8),
+        _ => None,
+    }
+}
+
+/// Keys with modifiers: the legacy bytes when there are, `modifyOtherKeys` if not
+fn modified(modifiers: Modifiers, code: KeyCode, mode: Mode) -> Option<Vec<u8>> {
+    let legacy = !modifiers.contains(Modifiers::SHIFT);
+    let prefix: &[u8] = match modifiers.contains(Modifiers::ALT) {
+        true => &[0x1B],
+        false => &[],
+    };
+    let without_alt = modifiers.without(Modifiers::ALT);
+    match (code, mode) {
+        (KeyCode::F(number), Mode::Normal | Mode::Application) => function(number, modifiers, mode),
+        (
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Right
+            | KeyCode::Left
+            | KeyCode::Home
+            | KeyCode::End,
+            Mode::Normal | Mode::Application,
+        ) => {
+            let final_byte = match code {
+                KeyCode::Up => 'A',
+                KeyCode::Down => 'B',
+                KeyCode::Right => 'C',
+                KeyCode::Left => 'D',
+                KeyCode::Home => 'H',
+                _ => 'F',
+            };
+            Some(format!("\x1b[1;{}{}", modifiers.to_xterm(), final_byte).into_bytes())
+        }
+        (KeyCode::Char(c), _) if legacy && without_alt == Modifiers::CTRL => {
+            let mut bytes = prefix.to_vec();
+            bytes.push(ctrl(c)?);
+            Some(bytes)
+        }
+        (code, _) if legacy && without_alt.is_empty() => {
+            let mut bytes = prefix.to_vec();
+            bytes.extend(encode(&Keys::from(code), mode)?);
+            Some(bytes)
+        }
+        (_, Mode::Linux) => None,
+        (code, _) => {
+            let code = match code {
+                KeyCode::Enter => 0x0D,
+                KeyCode::Tab => 0x09,
+                KeyCode::Space => 0x20,
+                KeyCode::Delete => 0x7F,
+                KeyCode::Escape => 0x1B,
+                KeyCode::Char(c) => c as u32,
+                _ => return None,
+            };
+            Some(format!("\x1b[27;{};{}~", modifiers.to_xterm(), code).into_bytes())
+        }
+    }
+}
+
+/// Bytes of the Ctrl, Alt or AltGr `key` in the enabled tables, so they are
+/// decoded back as the same key
+fn from_tables(key: Keys) -> Option<Vec<u8>> {
+    let tables: &[&[([u8; crate::keys::BYTES], Keys)]] = &[
+        #[cfg(any(feature = "ctrl_lower_letter", feature = "full"))]
+        &crate::keys::CTRL_LOWER_LETTER,
+        #[cfg(any(feature = "ctrl_upper_letter", feature = "full"))]
+        &crate::keys::CTRL_UPPER_LETTER,
+        #[cfg(all(
+            any(feature = "ctrl_standar", feature = "full"),
+            not(feature = "standar")
+        ))]
+        &crate::keys::CTRL_STANDAR,
+        #[cfg(any(feature = "ctrl_numbers", feature = "full"))]
+        &crate::keys::CTRL_NUMBERS,
+        #[cfg(any(feature = "alt_lower_letter", feature = "full"))]
+        &crate::keys::ALT_LOWER_LETTER,
+        #[cfg(any(feature = "alt_upper_letter", feature = "full"))]
+        &crate::keys::ALT_UPPER_LETTER,
+        #[cfg(any(feature = "alt_numbers", feature = "full"))]
+        &crate::keys::ALT_NUMBERS,
+        #[cfg(any(feature = "alt_gr_lower_letter", feature = "full"))]
+        &crate::keys::ALT_GR_LOWER_LETTER,
+        #[cfg(any(feature = "alt_gr_upper_letter", feature = "full"))]
+        &crate::keys::ALT_GR_UPPER_LETTER,
+        #[cfg(any(feature = "alt_gr_numbers", feature = "full"))]
+        &crate::keys::ALT_GR_NUMBERS,
+    ];
+    tables
+        .iter()
+        .flat_map(|table| table.iter())
+        .find(|(_, found)| *found == key)
+        .map(|(pattern, _)| {
+            let len = pattern
+                .iter()
+                .rposition(|&byte| byte != 0)
+                .map_or(0, |last| last + 1);
+            pattern[..len].to_vec()
+        })
+}
+
+fn utf8(c: char) -> Vec<u8> {
+    c.to_string().into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keyboard::decode, keys::BYTES};
+
+    /// Entries of the enabled keys tables, like `match_tables` looks them up
+    fn table_entries() -> Vec<([u8; BYTES], Keys)> {
+        let tables: &[&[([u8; BYTES], Keys)]] = &[
+            &crate::keys::ARROWS_ENTER,
+            &crate::keys::APPLICATION_CURSOR,
+            #[cfg(any(feature = "standar", feature = "full"))]
+            &crate::keys::STANDAR,
+            #[cfg(any(feature = "numbers", feature = "full"))]
+            &crate::keys::NUMBERS,
+            #[cfg(any(feature = "lower_letter", feature = "full"))]
+            &crate::keys::LOWER_LETTERS,
+            #[cfg(any(feature = "upper_letter", feature = "full"))]
+            &crate::keys::UPPER_LETTER,
+            #[cfg(any(feature = "f", feature = "full"))]
+            &crate::keys::F,
+            #[cfg(any(feature = "ctrl_lower_letter", feature = "full"))]
+            &crate::keys::CTRL_LOWER_LETTER,
+            #[cfg(any(feature = "ctrl_upper_letter", feature = "full"))]
+            &crate::keys::CTRL_UPPER_LETTER,
+            #[cfg(all(
+                any(feature = "ctrl_standar", feature = "full"),
+                not(feature = "standar")
+            ))]
+            &crate::keys::CTRL_STANDAR,
+            #[cfg(any(feature = "ctrl_numbers", feature = "full"))]
+            &crate::keys::CTRL_NUMBERS,
+            #[cfg(any(feature = "alt_lower_letter", feature = "full"))]
+            &crate::keys::ALT_LOWER_LETTER,
+            #[cfg(any(feature = "alt_upper_letter", feature = "full"))]
+            &crate::keys::ALT_UPPER_LETTER,
+            #[cfg(any(feature = "alt_numbers", feature = "full"))]
+            &crate::keys::ALT_NUMBERS,
+            #[cfg(any(feature = "alt_gr_lower_letter", feature = "full"))]
+            &crate::keys::ALT_GR_LOWER_LETTER,
+            #[cfg(any(feature = "alt_gr_upper_letter", feature = "full"))]
+            &crate::keys::ALT_GR_UPPER_LETTER,
+            #[cfg(any(feature = "alt_gr_numbers", feature = "full"))]
+            &crate::keys::ALT_GR_NUMBERS,
+        ];
+        tables
+            .iter()
+            .flat_map(|table| table.iter().copied())
+            .collect()
+    }
+
+    /// Every decoded key of the tables encodes to bytes decoded as the same key.
+    /// The key of a pattern is the one `decode` gives, as some tables share bytes
+    /// (`0` is `Char('0')` & `AltGr('0')`). Enter is left out, it's sent as `\r`
+    #[test]
+    fn table_keys_round_trip() {
+        for (pattern, _) in table_entries() {
+            let len = pattern
+                .iter()
+                .rposition(|&byte| byte != 0)
+                .map_or(1, |last| last + 1);
+            let (key, _) = decode(&pattern[..len], false);
+            if key == Keys::Enter {
+                continue;
+            }
+            for mode in [Mode::Normal, Mode::Application] {
+                let bytes = encode(&key, mode).unwrap();
+                assert_eq!(
+                    decode(&bytes, false),
+                    (key, bytes.len()),
+                    "{:?} in {:?}",
+                    key,
+                    mode
+                );
+            }
+        }
+    }
+
+    /// Every key code with the modifiers that have no legacy bytes, sent as the
+    /// xterm forms (`CSI 1 ; mod X`, `CSI n ; mod ~` & `CSI 27 ; mod ; code ~`)
+    /// & decoded as `Keys::with_modifiers` names them (`Shift + Tab` is `Backtab`)
+    #[test]
+    fn modified_keys_round_trip() {
+        let mut codes = vec![
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Enter,
+            KeyCode::Home,
+            KeyCode::Tab,
+            KeyCode::Space,
+            KeyCode::Delete,
+            KeyCode::Escape,
+            KeyCode::End,
+        ];
+        codes.extend(['a', 'Z', '0', 'ñ', '+'].map(KeyCode::Char));
+        codes.extend((1..=12).map(KeyCode::F));
+        let shift_combos = [
+            Modifiers::SHIFT,
+            Modifiers::SHIFT | Modifiers::ALT,
+            Modifiers::SHIFT | Modifiers::CTRL,
+            Modifiers::SHIFT | Modifiers::ALT | Modifiers::CTRL,
+        ];
+        for code in codes {
+            for modifiers in shift_combos {
+                let key = Keys::Modified(modifiers, code);
+                let expected = Keys::with_modifiers(code, modifiers);
+                for mode in [Mode::Normal, Mode::Application] {
+                    let bytes = encode(&key, mode).unwrap();
+                    assert_eq!(
+                        decode(&bytes, true),
+                        (expected, bytes.len()),
+                        "{:?} in {:?}",
+                        key,
+                        mode
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,482 @@
+/***************************************************************************************
+ *   evdev.rs  --  This file is part of k_board.                                       *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keys::{KeyCode, Keys, Modifiers},
+    termio::ioctl,
+};
+use std::{
+    collections::HashMap,
+    ffi::{c_int, c_long, c_ulong},
+    fs::{self, File},
+    io::{Error, ErrorKind, Read, Result},
+    mem::size_of,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Key & button events, the only type turned into `DeviceEvent`s
+pub const EV_KEY: u16 = 0x01;
+
+/// Bytes of a `struct input_event`: a `timeval`, the type, the code & the value
+pub const INPUT_EVENT_SIZE: usize = 2 * size_of::<c_long>() + 8;
+
+const KEY_ENTER: u16 = 28;
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_A: u16 = 30;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_Z: u16 = 44;
+const KEY_RIGHTSHIFT: u16 = 54;
+const KEY_LEFTALT: u16 = 56;
+const KEY_SPACE: u16 = 57;
+const KEY_CAPSLOCK: u16 = 58;
+const KEY_RIGHTCTRL: u16 = 97;
+const KEY_RIGHTALT: u16 = 100;
+
+/// Keys that make a device a keyboard: Q, A, Z, Enter & Space
+const KEYBOARD_KEYS: [u16; 5] = [16, KEY_A, KEY_Z, KEY_ENTER, KEY_SPACE];
+
+/// Bytes of the `EV_KEY` capability bits, `KEY_MAX / 8 + 1`
+const KEY_BITS: usize = 96;
+
+/// Bytes asked for the device name
+const NAME_BYTES: usize = 256;
+
+/// `EVIOCGRAB`, `_IOW('E', 0x90, int)`
+const EVIOCGRAB: c_ulong = (1 << 30) | ((size_of::<c_int>() as c_ulong) << 16) | (0x45 << 8) | 0x90;
+
+/// `_IOR('E', nr, [u8; size])`, the evdev ioctls reading a buffer
+const fn eviocg(nr: c_ulong, size: usize) -> c_ulong {
+    (2 << 30) | ((size as c_ulong) << 16) | (0x45 << 8) | nr
+}
+
+/// `struct input_event` as read from `/dev/input/event*`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InputEvent {
+    /// Time the kernel took the event, since the epoch by default
+    pub time: Duration,
+    /// `EV_KEY`, `EV_SYN`, `EV_MSC`..
+    pub kind: u16,
+    pub code: u16,
+    /// For `EV_KEY`: 0 up, 1 down & 2 autorepeat
+    pub value: i32,
+}
+
+impl InputEvent {
+    /// Read the event from its bytes in the native layout
+    pub fn from_bytes(bytes: &[u8; INPUT_EVENT_SIZE]) -> Self {
+        let long = size_of::<c_long>();
+        let number = |at: usize| c_long::from_ne_bytes(bytes[at..at + long].try_into().unwrap());
+        let (seconds, micros) = (number(0).max(0) as u64, number(long).max(0) as u64);
+        let rest = &bytes[2 * long..];
+        InputEvent {
+            time: Duration::from_secs(seconds) + Duration::from_micros(micros),
+            kind: u16::from_ne_bytes([rest[0], rest[1]]),
+            code: u16::from_ne_bytes([rest[2], rest[3]]),
+            value: i32::from_ne_bytes([rest[4], rest[5], rest[6], rest[7]]),
+        }
+    }
+
+    /// Bytes of the event as the kernel writes them, to record event files
+    pub fn to_bytes(&self) -> [u8; INPUT_EVENT_SIZE] {
+        let long = size_of::<c_long>();
+        let mut bytes = [0; INPUT_EVENT_SIZE];
+        let seconds = self.time.as_secs() as c_long;
+        let micros = self.time.subsec_micros() as c_long;
+        bytes[..long].copy_from_slice(&seconds.to_ne_bytes());
+        bytes[long..2 * long].copy_from_slice(&micros.to_ne_bytes());
+        bytes[2 * long..2 * long + 2].copy_from_slice(&self.kind.to_ne_bytes());
+        bytes[2 * long + 2..2 * long + 4].copy_from_slice(&self.code.to_ne_bytes());
+        bytes[2 * long + 4..].copy_from_slice(&self.value.to_ne_bytes());
+        bytes
+    }
+}
+
+/// State of a key in an `EV_KEY` event
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum KeyState {
+    Up,
+    Down,
+    /// Autorepeat of a key held down
+    Repeat,
+}
+
+impl KeyState {
+    fn from_value(value: i32) -> Self {
+        match value {
+            0 => KeyState::Up,
+            1 => KeyState::Down,
+            _ => KeyState::Repeat,
+        }
+    }
+}
+
+/// Key going down or up on an input device
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DeviceEvent {
+    /// Key of the layout with the modifiers held, `None` for keycodes out
+    /// of the layout, like Shift, Ctrl, Alt or mouse buttons
+    pub key: Option<Keys>,
+    /// Linux keycode (`KEY_*` in `input-event-codes.h`)
+    pub code: u16,
+    pub state: KeyState,
+    /// Time the kernel took the event
+    pub time: Duration,
+}
+
+/// Linux keycodes to keys, with & without Shift
+#[derive(Debug, Clone)]
+pub struct Layout {
+    keys: HashMap<u16, (KeyCode, KeyCode)>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::us()
+    }
+}
+
+impl Layout {
+    /// Layout without keys, to fill with `key`
+    pub fn empty() -> Self {
+        Layout {
+            keys: HashMap::new(),
+        }
+    }
+
+    /// US QWERTY, the default. The keypad gives its digits whatever Num Lock is
+    pub fn us() -> Self {
+        let chars = US_CHARS
+            .iter()
+            .map(|&(code, key, shifted)| (code, (KeyCode::Char(key), KeyCode::Char(shifted))));
+        let keys = US_KEYS.iter().map(|&(code, key)| (code, (key, key)));
+        let f = (59..=68)
+            .chain([87, 88])
+            .zip(1..=12)
+            .map(|(code, f)| (code, (KeyCode::F(f), KeyCode::F(f))));
+        Layout {
+            keys: chars.chain(keys).chain(f).collect(),
+        }
+    }
+
+    /// Map `code` to `key`, or to `shifted` while Shift is held
+    pub fn key(mut self, code: u16, key: KeyCode, shifted: KeyCode) -> Self {
+        self.keys.insert(code, (key, shifted));
+        self
+    }
+
+    /// Key of `code` with or without Shift
+    pub fn get(&self, code: u16, shift: bool) -> Option<KeyCode> {
+        let &(key, shifted) = self.keys.get(&code)?;
+        Some(if shift { shifted } else { key })
+    }
+}
+
+/// Chars of the US layout, without & with Shift
+const US_CHARS: [(u16, char, char); 60] = [
+    (2, '1', '!'),
+    (3, '2', '@'),
+    (4, '3', '#'),
+    (5, '4', '$'),
+    (6, '5', '%'),
+    (7, '6', '^'),
+    (8, '7', '&'),
+    (9, '8', '*'),
+    (10, '9', '('),
+    (11, '0', ')'),
+    (12, '-', '_'),
+    (13, '=', '+'),
+    (16, 'q', 'Q'),
+    (17, 'w', 'W'),
+    (18, 'e', 'E'),
+    (19, 'r', 'R'),
+    (20, 't', 'T'),
+    (21, 'y', 'Y'),
+    (22, 'u', 'U'),
+    (23, 'i', 'I'),
+    (24, 'o', 'O'),
+    (25, 'p', 'P'),
+    (26, '[', '{'),
+    (27, ']', '}'),
+    (30, 'a', 'A'),
+    (31, 's', 'S'),
+    (32, 'd', 'D'),
+    (33, 'f', 'F'),
+    (34, 'g', 'G'),
+    (35, 'h', 'H'),
+    (36, 'j', 'J'),
+    (37, 'k', 'K'),
+    (38, 'l', 'L'),
+    (39, ';', ':'),
+    (40, '\'', '"'),
+    (41, '`', '~'),
+    (43, '\\', '|'),
+    (44, 'z', 'Z'),
+    (45, 'x', 'X'),
+    (46, 'c', 'C'),
+    (47, 'v', 'V'),
+    (48, 'b', 'B'),
+    (49, 'n', 'N'),
+    (50, 'm', 'M'),
+    (51, ',', '<'),
+    (52, '.', '>'),
+    (53, '/', '?'),
+    (55, '*', '*'),
+    (71, '7', '7'),
+    (72, '8', '8'),
+    (73, '9', '9'),
+    (74, '-', '-'),
+    (75, '4', '4'),
+    (76, '5', '5'),
+    (77, '6', '6'),
+    (78, '+', '+'),
+    (79, '1', '1'),
+    (80, '2', '2'),
+    (81, '3', '3'),
+    (82, '0', '0'),
+];
+
+/// Keys of the US layout that are not chars. F1..F12 are added apart
+const US_KEYS: [(u16, KeyCode); 14] = [
+    (1, KeyCode::Escape),
+    (14, KeyCode::Delete),
+    (15, KeyCode::Tab),
+    (KEY_ENTER, KeyCode::Enter),
+    (KEY_SPACE, KeyCode::Space),
+    (83, KeyCode::Char('.')),
+    (96, KeyCode::Enter),
+    (98, KeyCode::Char('/')),
+    (102, KeyCode::Home),
+    (103, KeyCode::Up),
+    (105, KeyCode::Left),
+    (106, KeyCode::Right),
+    (107, KeyCode::End),
+    (108, KeyCode::Down),
+];
+
+/// Event device found in `/dev/input`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeviceInfo {
+    pub path: PathBuf,
+    pub name: String,
+    /// It has the keys of a keyboard
+    pub keyboard: bool,
+}
+
+/// Event devices that can be opened (root or the `input` group can),
+/// sorted by number
+pub fn devices() -> Result<Vec<DeviceInfo>> {
+    let mut paths: Vec<(u32, PathBuf)> = fs::read_dir("/dev/input")?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let number = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("event")?
+                .parse()
+                .ok()?;
+            Some((number, path))
+        })
+        .collect();
+    paths.sort();
+    let mut devices = Vec::new();
+    let mut last_error = None;
+    for (_, path) in paths {
+        match File::open(&path).and_then(|file| info(&file, path)) {
+            Ok(device) => devices.push(device),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    match (devices.is_empty(), last_error) {
+        (true, Some(err)) => Err(err),
+        _ => Ok(devices),
+    }
+}
+
+/// Name & capabilities of the device open in `file`
+fn info(file: &File, path: PathBuf) -> Result<DeviceInfo> {
+    let fd = file.as_raw_fd();
+    let mut name = [0u8; NAME_BYTES];
+    if unsafe { ioctl(fd, eviocg(0x06, NAME_BYTES), name.as_mut_ptr()) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    let len = name
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(NAME_BYTES);
+    let mut bits = [0u8; KEY_BITS];
+    let key_bits = eviocg(0x20 + EV_KEY as c_ulong, KEY_BITS);
+    if unsafe { ioctl(fd, key_bits, bits.as_mut_ptr()) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    let has = |code: u16| bits[code as usize / 8] & (1 << (code % 8)) != 0;
+    Ok(DeviceInfo {
+        path,
+        name: String::from_utf8_lossy(&name[..len]).into_owned(),
+        keyboard: KEYBOARD_KEYS.into_iter().all(has),
+    })
+}
+
+/// Key events of an input device, or of a file recorded from one
+/// (`cat /dev/input/event3 > keys`), with the modifiers & Caps Lock
+/// applied through the layout
+pub struct Device<R = File> {
+    input: R,
+    layout: Layout,
+    /// Modifier keys held down, by keycode
+    held: Vec<u16>,
+    caps_lock: bool,
+}
+
+impl Device {
+    /// Open an event device, like `/dev/input/event3`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Device::from_reader(File::open(path)?))
+    }
+
+    /// Open the first device whose name contains `name`
+    pub fn find(name: &str) -> Result<Self> {
+        Self::open_first(|device| device.name.contains(name))
+    }
+
+    /// Open the first device with the keys of a keyboard
+    pub fn keyboard() -> Result<Self> {
+        Self::open_first(|device| device.keyboard)
+    }
+
+    fn open_first(found: impl Fn(&DeviceInfo) -> bool) -> Result<Self> {
+        match devices()?.into_iter().find(found) {
+            Some(device) => Device::open(device.path),
+            None => Err(Error::new(ErrorKind::NotFound, "no input device found")),
+        }
+    }
+
+    /// Take the keys of the device for this program only (`EVIOCGRAB`), so
+    /// they reach no terminal, until `grab(false)` or the device is dropped
+    pub fn grab(&self, grab: bool) -> Result<()> {
+        if unsafe { ioctl(self.input.as_raw_fd(), EVIOCGRAB, grab as c_int) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Device<R> {
+    /// Read the events of a recorded file or any other reader
+    pub fn from_reader(input: R) -> Self {
+        Device {
+            input,
+            layout: Layout::default(),
+            held: Vec::new(),
+            caps_lock: false,
+        }
+    }
+
+    /// Layout to turn keycodes into keys, US QWERTY by default
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Modifiers held down, Right Alt is AltGr for chars & Alt for other keys
+    pub fn modifiers(&self) -> Modifiers {
+        let held = |codes: [u16; 2]| codes.iter().any(|code| self.held.contains(code));
+        let mut modifiers = Modifiers::NONE;
+        if held([KEY_LEFTSHIFT, KEY_RIGHTSHIFT]) {
+            modifiers = modifiers | Modifiers::SHIFT;
+        }
+        if held([KEY_LEFTCTRL, KEY_RIGHTCTRL]) {
+            modifiers = modifiers | Modifiers::CTRL;
+        }
+        if held([KEY_LEFTALT, KEY_RIGHTALT]) {
+            modifiers = modifiers | Modifiers::ALT;
+        }
+        modifiers
+    }
+
+    /// Next event of any type, `None` at the end of the input
+    pub fn read_event(&mut self) -> Result<Option<InputEvent>> {
+        let mut bytes = [0; INPUT_EVENT_SIZE];
+        let mut filled = 0;
+        while filled < INPUT_EVENT_SIZE {
+            match self.input.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Some(InputEvent::from_bytes(&bytes)))
+    }
+
+    /// Next key going down, up or autorepeating, `None` at the end of the input
+    pub fn next_key(&mut self) -> Result<Option<DeviceEvent>> {
+        while let Some(event) = self.read_event()? {
+            if event.kind == EV_KEY {
+                return Ok(Some(self.translate(event)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Keep the modifiers & Caps Lock, then look the key up in the layout
+    fn translate(&mut self, event: InputEvent) -> DeviceEvent {
+        let state = KeyState::from_value(event.value);
+        match event.code {
+            KEY_LEFTSHIFT | KEY_RIGHTSHIFT | KEY_LEFTCTRL | KEY_RIGHTCTRL | KEY_LEFTALT
+            | KEY_RIGHTALT => {
+                self.held.retain(|&code| code != event.code);
+                if state != KeyState::Up {
+                    self.held.push(event.code);
+                }
+            }
+            KEY_CAPSLOCK if state == KeyState::Down => self.caps_lock = !self.caps_lock,
+            _ => {}
+        }
+        DeviceEvent {
+            key: self.key(event.code),
+            code: event.code,
+            state,
+            time: event.time,
+        }
+    }
+
+    fn key(&self, code: u16) -> Option<Keys> {
+        let modifiers = self.modifiers();
+        let mut shift = modifiers.contains(Modifiers::SHIFT);
+        if let Some(KeyCode::Char(c)) = self.layout.get(code, false) {
+            shift ^= self.caps_lock && c.is_alphabetic();
+        }
+        let key = self.layout.get(code, shift)?;
+        match key {
+            KeyCode::Char(c) if self.held.contains(&KEY_RIGHTALT) => Some(Keys::AltGr(c)),
+            _ => Some(Keys::with_modifiers(key, modifiers)),
+        }
+    }
+}
+
+impl<R: Read> Iterator for Device<R> {
+    type Item = Result<DeviceEvent>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_key().transpose()
+    }
+}
@@ -0,0 +1,157 @@
+/***************************************************************************************
+ *   event.rs  --  This file is part of k_board.                                       *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::keys::Keys;
+use std::time::{Duration, Instant};
+
+/// Key read from the keyboard with the moment it arrived
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyEvent {
+    pub key: Keys,
+    /// Monotonic time of the read that brought the key,
+    /// not serialized (`Instant::now()` when deserialized)
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+    pub time: Instant,
+    /// Time since the previous key, `None` for the first one
+    pub since_previous: Option<Duration>,
+    /// Same key as the previous one
+    pub repeat: bool,
+    /// Presses of this key in a row, each one inside the multi press window
+    /// of the previous: 1 single, 2 double, 3 triple...
+    pub presses: u8,
+}
+
+impl KeyEvent {
+    /// Stamp `key` read at `time`, grouping it with `previous` when it is the same
+    /// key pressed again inside `window`
+    pub(crate) fn new(
+        key: Keys,
+        time: Instant,
+        previous: Option<&KeyEvent>,
+        window: Duration,
+    ) -> Self {
+        let since_previous = previous.map(|previous| time.duration_since(previous.time));
+        let repeat = previous.is_some_and(|previous| previous.key == key);
+        let presses = match (previous, since_previous) {
+            (Some(previous), Some(elapsed)) if repeat && elapsed <= window => {
+                previous.presses.saturating_add(1)
+            }
+            _ => 1,
+        };
+        KeyEvent {
+            key,
+            time,
+            since_previous,
+            repeat,
+            presses,
+        }
+    }
+
+    /// Second press in a row inside the window
+    pub fn is_double(&self) -> bool {
+        self.presses == 2
+    }
+
+    /// Third press in a row inside the window
+    pub fn is_triple(&self) -> bool {
+        self.presses == 3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(300);
+
+    /// Events of `keys` pressed at the given milliseconds after the start
+    fn events(keys: &[(Keys, u64)]) -> Vec<KeyEvent> {
+        let start = Instant::now();
+        let mut previous: Option<KeyEvent> = None;
+        keys.iter()
+            .map(|&(key, at)| {
+                let time = start + Duration::from_millis(at);
+                let event = KeyEvent::new(key, time, previous.as_ref(), WINDOW);
+                previous = Some(event);
+                event
+            })
+            .collect()
+    }
+
+    fn presses(keys: &[(Keys, u64)]) -> Vec<u8> {
+        events(keys).iter().map(|event| event.presses).collect()
+    }
+
+    #[test]
+    fn presses_inside_the_window() {
+        let a = Keys::Char('a');
+        let events = events(&[(a, 0), (a, 100), (a, 400)]);
+        assert_eq!(events[0].since_previous, None);
+        assert!(!events[0].repeat);
+        assert_eq!(events[1].since_previous, Some(Duration::from_millis(100)));
+        assert!(events[1].repeat && events[1].is_double());
+        assert_eq!(events[2].since_previous, Some(Duration::from_millis(300)));
+        assert!(events[2].repeat && events[2].is_triple());
+    }
+
+    #[test]
+    fn presses_outside_the_window() {
+        let a = Keys::Char('a');
+        let events = events(&[(a, 0), (a, 301), (a, 500)]);
+        assert!(events[1].repeat);
+        assert_eq!(
+            events.iter().map(|event| event.presses).collect::<Vec<_>>(),
+            [1, 1, 2]
+        );
+        assert!(!events[1].is_double() && events[2].is_double());
+    }
+
+    #[test]
+    fn other_key_breaks_the_run() {
+        let (a, b) = (Keys::Char('a'), Keys::Char('b'));
+        let events = events(&[(a, 0), (b, 50), (a, 100), (a, 150)]);
+        assert_eq!(
+            events.iter().map(|event| event.presses).collect::<Vec<_>>(),
+            [1, 1, 1, 2]
+        );
+        assert!(!events[1].repeat && !events[2].repeat && events[3].repeat);
+    }
+
+    #[test]
+    fn count_after_a_triple() {
+        let a = Keys::Char('a');
+        assert_eq!(
+            presses(&[(a, 0), (a, 100), (a, 200), (a, 300)]),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            presses(&[(a, 0), (a, 100), (a, 200), (a, 600), (a, 700)]),
+            [1, 2, 3, 1, 2]
+        );
+        let b = Keys::Char('b');
+        assert_eq!(
+            presses(&[(a, 0), (a, 100), (a, 200), (b, 250), (a, 300)]),
+            [1, 2, 3, 1, 1]
+        );
+        let fourth = &events(&[(a, 0), (a, 100), (a, 200), (a, 300)])[3];
+        assert!(!fourth.is_double() && !fourth.is_triple());
+    }
+}
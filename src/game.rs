@@ -0,0 +1,304 @@
+/***************************************************************************************
+ *   game.rs  --  This file is part of k_board.                                        *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    event::KeyEvent,
+    keyboard::Keyboard,
+    keys::Keys,
+    termio::{poll, pollfd, raw_mode_fd, RawMode, POLLIN},
+};
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::{Duration, Instant},
+};
+
+/// Default time a pressed key stays down waiting for the first autorepeat
+const HOLD_DELAY: Duration = Duration::from_millis(700);
+
+/// Default max time between autorepeats of a held key
+const REPEAT_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Key taken as held down
+struct Held {
+    key: Keys,
+    /// Last press or autorepeat
+    last: Instant,
+    repeating: bool,
+}
+
+/// Input of a game loop: `update` once per tick takes the keys pending
+/// without blocking & keeps the keys held down. Terminals send no key
+/// releases, so a key is down while its autorepeats keep arriving & only
+/// the last key pressed repeats
+pub struct GameInput {
+    keyboard: Keyboard,
+    _raw_mode: Option<RawMode>,
+    hold_delay: Duration,
+    repeat_timeout: Duration,
+    held: Vec<Held>,
+    events: Vec<KeyEvent>,
+    pressed: Vec<Keys>,
+    released: Vec<Keys>,
+}
+
+impl GameInput {
+    /// Keep the terminal of `keyboard` in raw mode until dropped.
+    /// Ctrl + C arrives as a key, like in `Keyboard::spawn`
+    pub fn new(mut keyboard: Keyboard) -> Result<Self> {
+        let raw_mode = raw_mode_fd(keyboard.fd(), false, &[])?;
+        keyboard.set_modify_other_keys(true)?;
+        Ok(GameInput::with_raw_mode(keyboard, Some(raw_mode)))
+    }
+
+    fn with_raw_mode(keyboard: Keyboard, raw_mode: Option<RawMode>) -> Self {
+        GameInput {
+            keyboard,
+            _raw_mode: raw_mode,
+            hold_delay: HOLD_DELAY,
+            repeat_timeout: REPEAT_TIMEOUT,
+            held: Vec::new(),
+            events: Vec::new(),
+            pressed: Vec::new(),
+            released: Vec::new(),
+        }
+    }
+
+    /// Time a pressed key stays down waiting for the first autorepeat
+    /// (700ms by default), above the autorepeat delay of the terminal
+    pub fn hold_delay(mut self, delay: Duration) -> Self {
+        self.hold_delay = delay;
+        self
+    }
+
+    /// Max time between autorepeats of a held key (150ms by default),
+    /// above the autorepeat rate of the terminal
+    pub fn repeat_timeout(mut self, timeout: Duration) -> Self {
+        self.repeat_timeout = timeout;
+        self
+    }
+
+    /// Start a new frame: take all the key events pending without blocking
+    /// & release the keys whose autorepeat stopped. The end of the input is
+    /// an `UnexpectedEof` error
+    pub fn update(&mut self) -> Result<()> {
+        self.update_at(Instant::now())
+    }
+
+    /// `update` with the frame starting at `now`, like a fixed timestep
+    /// clock. The keys still get the time they were read at
+    pub fn update_at(&mut self, now: Instant) -> Result<()> {
+        self.events.clear();
+        self.pressed.clear();
+        self.released.clear();
+        self.drain()?;
+        let (hold_delay, repeat_timeout) = (self.hold_delay, self.repeat_timeout);
+        let released = &mut self.released;
+        self.held.retain(|held| {
+            let up = held.up(now, hold_delay, repeat_timeout);
+            if up {
+                released.push(held.key);
+            }
+            !up
+        });
+        Ok(())
+    }
+
+    /// Read the terminal while it has bytes, without waiting
+    fn drain(&mut self) -> Result<()> {
+        loop {
+            while let Some(event) = self.keyboard.pending_event() {
+                if event.key != Keys::Null {
+                    self.press(event);
+                }
+            }
+            let mut fd = pollfd {
+                fd: self.keyboard.fd(),
+                events: POLLIN,
+                revents: 0,
+            };
+            match unsafe { poll(&mut fd, 1, 0) } {
+                0 => return Ok(()),
+                polled if polled < 0 => {
+                    let err = Error::last_os_error();
+                    if err.kind() != ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+                _ if fd.revents & POLLIN == 0 || self.keyboard.fill()? == 0 => {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Key event of this frame: a press, or an autorepeat of a held key
+    fn press(&mut self, event: KeyEvent) {
+        self.events.push(event);
+        let (hold_delay, repeat_timeout) = (self.hold_delay, self.repeat_timeout);
+        match self.held.iter().position(|held| held.key == event.key) {
+            Some(index) if !self.held[index].up(event.time, hold_delay, repeat_timeout) => {
+                let held = &mut self.held[index];
+                held.last = event.time;
+                held.repeating = true;
+            }
+            index => {
+                if let Some(index) = index {
+                    self.held.remove(index);
+                    self.released.push(event.key);
+                }
+                self.held.push(Held {
+                    key: event.key,
+                    last: event.time,
+                    repeating: false,
+                });
+                self.pressed.push(event.key);
+            }
+        }
+    }
+
+    /// Key events of this frame in the order they arrived, autorepeats included
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+
+    /// `key` went down in this frame
+    pub fn pressed_this_frame(&self, key: Keys) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// `key` is held down
+    pub fn is_down(&self, key: Keys) -> bool {
+        self.held.iter().any(|held| held.key == key)
+    }
+
+    /// `key` went up in this frame
+    pub fn released_this_frame(&self, key: Keys) -> bool {
+        self.released.contains(&key)
+    }
+}
+
+impl Held {
+    /// No autorepeat arrived in time by `now`
+    fn up(&self, now: Instant, hold_delay: Duration, repeat_timeout: Duration) -> bool {
+        let timeout = match self.repeating {
+            true => repeat_timeout,
+            false => hold_delay,
+        };
+        now.saturating_duration_since(self.last) > timeout
+    }
+}
+
+impl Drop for GameInput {
+    fn drop(&mut self) {
+        let _ = self.keyboard.set_modify_other_keys(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::decode;
+    use std::{
+        fs::File,
+        io::Write,
+        os::{fd::OwnedFd, unix::net::UnixStream},
+    };
+
+    /// Game input reading a socket instead of a terminal, & the other end
+    fn game_input() -> (GameInput, UnixStream) {
+        let (terminal, input) = UnixStream::pair().unwrap();
+        let keyboard = Keyboard::from_file(File::from(OwnedFd::from(input)));
+        (GameInput::with_raw_mode(keyboard, None), terminal)
+    }
+
+    fn key(bytes: &[u8]) -> Keys {
+        decode(bytes, false).0
+    }
+
+    /// Time of the last key read in the frame
+    fn last_read(input: &GameInput) -> Instant {
+        input.events().last().unwrap().time
+    }
+
+    #[test]
+    fn autorepeats_between_frames() {
+        let (input, mut terminal) = game_input();
+        // long timeouts & frames timed from the reads, so a slow run changes nothing
+        let (hold_delay, repeat_timeout) = (Duration::from_secs(600), Duration::from_secs(60));
+        let mut input = input.hold_delay(hold_delay).repeat_timeout(repeat_timeout);
+        let just_after = Duration::from_millis(1);
+        let a = key(b"a");
+        let start = Instant::now();
+        terminal.write_all(b"aaaa").unwrap();
+        input.update_at(start).unwrap();
+        assert_eq!(input.events().len(), 4);
+        assert!(input.events().iter().all(|event| event.key == a));
+        assert!(input.pressed_this_frame(a));
+        assert!(input.is_down(a));
+        assert!(!input.released_this_frame(a));
+
+        terminal.write_all(b"aaa").unwrap();
+        input.update_at(start).unwrap();
+        assert!(!input.pressed_this_frame(a));
+        assert!(input.is_down(a));
+        let last = last_read(&input);
+
+        input.update_at(last + repeat_timeout).unwrap();
+        assert!(input.is_down(a));
+        assert!(input.events().is_empty());
+        input.update_at(last + repeat_timeout + just_after).unwrap();
+        assert!(input.released_this_frame(a));
+        assert!(!input.is_down(a));
+
+        // a single press waits for the hold delay
+        terminal.write_all(b"a").unwrap();
+        input.update_at(start).unwrap();
+        assert!(input.pressed_this_frame(a));
+        let last = last_read(&input);
+        input.update_at(last + repeat_timeout + just_after).unwrap();
+        assert!(input.is_down(a));
+        input.update_at(last + hold_delay + just_after).unwrap();
+        assert!(input.released_this_frame(a));
+    }
+
+    #[test]
+    fn many_keys_in_one_frame() {
+        let (mut input, mut terminal) = game_input();
+        let (a, b) = (key(b"a"), key(b"b"));
+        terminal.write_all(b"ab\x1b[A").unwrap();
+        input.update().unwrap();
+        for key in [a, b, Keys::Up] {
+            assert!(input.pressed_this_frame(key), "{:?}", key);
+            assert!(input.is_down(key), "{:?}", key);
+        }
+        input.update().unwrap();
+        assert!(!input.pressed_this_frame(a));
+        assert!(input.is_down(a));
+    }
+
+    #[test]
+    fn end_of_input() {
+        let (mut input, terminal) = game_input();
+        drop(terminal);
+        let err = input.update().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,317 @@
+/***************************************************************************************
+ *   history.rs  --  This file is part of k_board.                                     *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::termio::{flock, LOCK_EX, LOCK_SH};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, Read, Result, Seek, SeekFrom, Write},
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+/// Default max entries kept
+const MAX_LEN: usize = 1000;
+
+/// Lines entered in the `LineEditor`, oldest first
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Vec<String>,
+    max_len: usize,
+    dedup: bool,
+    /// Entries already in the file
+    saved: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    /// Get an empty history of up to 1000 entries, without duplicates
+    pub fn new() -> Self {
+        History {
+            entries: Vec::new(),
+            max_len: MAX_LEN,
+            dedup: true,
+            saved: 0,
+        }
+    }
+
+    /// Max entries kept, the oldest ones are dropped
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self.truncate();
+        self
+    }
+
+    /// Remove the older copies of a line when it is entered again (on by default)
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Add a line, ignoring empty ones. False if it was ignored
+    pub fn add(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return false;
+        }
+        if self.dedup {
+            if let Some(index) = self.entries.iter().position(|entry| entry == line) {
+                self.entries.remove(index);
+                self.saved = self.saved.min(index);
+            }
+        }
+        self.entries.push(String::from(line));
+        self.truncate();
+        true
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.saved = 0;
+    }
+
+    /// Index of the newest entry before `before` that contains `query`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    /// Replace the entries with the ones in the file, under a shared lock.
+    /// A missing file is an empty history
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                self.clear();
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        lock(&file, LOCK_SH)?;
+        let entries = read_entries(&mut file)?;
+        self.clear();
+        for entry in entries {
+            self.add(&entry);
+        }
+        self.saved = self.entries.len();
+        Ok(())
+    }
+
+    /// Add the lines entered since the last load or save to the file, under an
+    /// exclusive lock, keeping the lines other sessions saved in the meantime
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        lock(&file, LOCK_EX)?;
+        let mut merged = History::new().max_len(self.max_len).dedup(self.dedup);
+        for entry in read_entries(&mut file)?
+            .iter()
+            .chain(&self.entries[self.saved.min(self.entries.len())..])
+        {
+            merged.add(entry);
+        }
+        let mut text = String::new();
+        for entry in &merged.entries {
+            text.push_str(&escape(entry));
+            text.push('\n');
+        }
+        file.seek(SeekFrom::Start(0))?;
+        file.set_len(0)?;
+        file.write_all(text.as_bytes())?;
+        file.flush()?;
+        self.saved = self.entries.len();
+        Ok(())
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > self.max_len {
+            let extra = self.entries.len() - self.max_len;
+            self.entries.drain(..extra);
+            self.saved = self.saved.saturating_sub(extra);
+        }
+    }
+}
+
+/// Lock the file until it is closed
+fn lock(file: &File, operation: i32) -> Result<()> {
+    if unsafe { flock(file.as_raw_fd(), operation) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// One entry per line, with `\n` & `\\` escaped
+fn read_entries(file: &mut File) -> Result<Vec<String>> {
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(unescape)
+        .collect())
+}
+
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    fn history(lines: &[&str]) -> History {
+        let mut history = History::new();
+        for line in lines {
+            history.add(line);
+        }
+        history
+    }
+
+    /// Path of a history file that doesn't exist yet
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("k_board-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn add_and_dedup() {
+        let mut history = history(&["a", "b", "a"]);
+        assert_eq!(history.entries(), ["b", "a"]);
+        assert!(!history.add(""));
+        assert!(!history.add("  "));
+        assert_eq!(history.len(), 2);
+        let mut copies = History::new().dedup(false);
+        copies.add("a");
+        copies.add("a");
+        assert_eq!(copies.entries(), ["a", "a"]);
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn max_len_drops_the_oldest() {
+        let mut history = History::new().max_len(2);
+        for line in ["a", "b", "c"] {
+            history.add(line);
+        }
+        assert_eq!(history.entries(), ["b", "c"]);
+        let history = self::history(&["a", "b", "c", "d"]).max_len(1);
+        assert_eq!(history.entries(), ["d"]);
+        assert_eq!(history.get(0), Some("d"));
+        assert_eq!(history.get(1), None);
+    }
+
+    #[test]
+    fn search_before() {
+        let history = history(&["cargo build", "ls", "cargo test", "cd"]);
+        assert_eq!(history.search("cargo", 4), Some(2));
+        assert_eq!(history.search("cargo", 2), Some(0));
+        assert_eq!(history.search("cargo", 0), None);
+        assert_eq!(history.search("c", 100), Some(3));
+        assert_eq!(history.search("make", 4), None);
+    }
+
+    #[test]
+    fn escapes() {
+        for entry in ["plain", "two\nlines", "back\\slash", "\\n", "end\\", "\n\n"] {
+            assert!(!escape(entry).contains('\n'), "{:?}", entry);
+            assert_eq!(unescape(&escape(entry)), entry);
+        }
+        assert_eq!(escape("a\\b\nc"), "a\\\\b\\nc");
+        assert_eq!(unescape("a\\xb\\"), "a\\xb\\");
+    }
+
+    #[test]
+    fn save_merges_other_sessions() -> Result<()> {
+        let path = temp_file("merge");
+        let mut first = History::new();
+        first.load(&path)?;
+        assert!(first.is_empty());
+        first.add("first");
+        let mut second = History::new();
+        second.load(&path)?;
+        second.add("second");
+        second.add("shared");
+        second.save(&path)?;
+        first.add("two\nlines");
+        first.add("shared");
+        first.save(&path)?;
+        let mut loaded = History::new();
+        loaded.load(&path)?;
+        assert_eq!(
+            loaded.entries(),
+            ["second", "first", "two\nlines", "shared"]
+        );
+        first.add("again");
+        first.save(&path)?;
+        loaded.load(&path)?;
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(loaded.get(4), Some("again"));
+        let mut short = History::new().max_len(2);
+        short.load(&path)?;
+        assert_eq!(short.entries(), ["shared", "again"]);
+        fs::remove_file(&path)
+    }
+}
@@ -19,10 +19,20 @@
  **************************************************************************************/
 
 use crate::{
-    keys::{Keys, ARROWS_ENTER, BYTES},
-    termio::{restore, setup_raw_mode, termios},
+    event::KeyEvent,
+    keys::{KeyCode, Keys, Modifiers, RawBytes, APPLICATION_CURSOR, ARROWS_ENTER, BYTES},
+    query::{find_response, Query, Response, SYNCHRONIZED_OUTPUT},
+    termio::{
+        read, restore_fd, setup_raw_mode_fd, termios, MODIFY_OTHER_KEYS_OFF, MODIFY_OTHER_KEYS_ON,
+    },
+};
+use std::{
+    ffi::c_int,
+    fs::{File, OpenOptions},
+    io::{stdout, Error, Read, Result, Write},
+    os::unix::io::AsRawFd,
+    time::{Duration, Instant},
 };
-use std::io::{stdin, stdout, Read, Write};
 
 #[cfg(any(
     feature = "ctrl_lower_letter",
@@ -31,12 +41,7 @@ use std::io::{stdin, stdout, Read, Write};
 ))]
 use crate::termio::{sig_handler, signal, SIGINT};
 
-#[cfg(any(
-    feature = "ctrl_lower_letter",
-    feature = "ctrl_upper_letter",
-    feature = "full"
-))]
-use std::sync::Mutex;
+use std::sync::{Mutex, PoisonError};
 
 /// Global varible to get the CTRL+C event
 #[cfg(any(
@@ -46,8 +51,29 @@ use std::sync::Mutex;
 ))]
 pub(crate) static CTRL_C: Mutex<bool> = Mutex::new(false);
 
+/// Max bytes taken from the terminal in one read
+const READ_BYTES: usize = 32;
+
+/// Bytes read by `get_key_from_keyboard` that belong to the following keys
+static PENDING: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Default time between presses of the same key to group them
+/// as a double or triple press
+const MULTI_PRESS_WINDOW: Duration = Duration::from_millis(300);
+
 /// Keyboard struct
-pub struct Keyboard;
+pub struct Keyboard {
+    modify_other_keys: bool,
+    pending: Vec<u8>,
+    read_time: Instant,
+    multi_press_window: Duration,
+    last_event: Option<KeyEvent>,
+    /// Terminal read instead of stdin
+    tty: Option<File>,
+    /// DECCKM mode asked for, sent before the first read
+    cursor_keys_mode: Option<bool>,
+    application_cursor_keys: bool,
+}
 
 impl Default for Keyboard {
     fn default() -> Self {
@@ -58,109 +84,573 @@ impl Default for Keyboard {
 impl Keyboard {
     /// Get a Keyboard instance
     pub fn new() -> Self {
-        Keyboard
+        Keyboard {
+            modify_other_keys: false,
+            pending: Vec::new(),
+            read_time: Instant::now(),
+            multi_press_window: MULTI_PRESS_WINDOW,
+            last_event: None,
+            tty: None,
+            cursor_keys_mode: None,
+            application_cursor_keys: false,
+        }
+    }
+
+    /// Get a Keyboard reading the keys from `/dev/tty` instead of stdin, so
+    /// stdin can be a pipe. Queries are written to the tty too
+    pub fn from_tty() -> Result<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let mut keyboard = Self::new();
+        keyboard.tty = Some(tty);
+        Ok(keyboard)
+    }
+
+    /// Keyboard reading `tty`, that can be any file in tests
+    #[cfg(test)]
+    pub(crate) fn from_file(tty: File) -> Self {
+        let mut keyboard = Self::new();
+        keyboard.tty = Some(tty);
+        keyboard
+    }
+
+    /// File descriptor of the terminal read
+    pub fn fd(&self) -> c_int {
+        self.tty.as_ref().map_or(0, AsRawFd::as_raw_fd)
+    }
+
+    /// Max time between presses of the same key to count them
+    /// as a double or triple press (300ms by default)
+    pub fn multi_press_window(mut self, window: Duration) -> Self {
+        self.multi_press_window = window;
+        self
+    }
+
+    /// Wait for the next key & stamp it with the time it arrived.
+    /// `Keys::Null` is returned as is, without counting as a key press
+    pub fn next_event(&mut self) -> KeyEvent {
+        let key = self.read_key();
+        self.stamp(key)
+    }
+
+    /// Stamp `key` with the time of the last read, keeping it as the last event
+    fn stamp(&mut self, key: Keys) -> KeyEvent {
+        let event = KeyEvent::new(
+            key,
+            self.read_time,
+            self.last_event.as_ref(),
+            self.multi_press_window,
+        );
+        if key != Keys::Null {
+            self.last_event = Some(event);
+        }
+        event
+    }
+
+    /// Iterator over the key events
+    pub fn events(&mut self) -> impl Iterator<Item = KeyEvent> + '_ {
+        std::iter::from_fn(move || Some(self.next_event()))
+    }
+
+    /// Last key event read with `next_event`
+    pub fn last_event(&self) -> Option<&KeyEvent> {
+        self.last_event.as_ref()
+    }
+
+    /// Time since the last key read with `next_event`
+    pub fn since_last_key(&self) -> Option<Duration> {
+        self.last_event.map(|event| event.time.elapsed())
+    }
+
+    /// Enable xterm `modifyOtherKeys` while waiting for a key, so Ctrl + number,
+    /// Ctrl + symbol or Shift + Enter arrive as their own events
+    /// (`Keys::Ctrl('1')`, `Keys::Ctrl('+')`, `Keys::Modified(..)`).
+    /// It is turned off again each time the termios is restored
+    pub fn modify_other_keys(mut self, enable: bool) -> Self {
+        self.modify_other_keys = enable;
+        self
+    }
+
+    /// Set (`CSI ? 1 h`) or reset (`CSI ? 1 l`) the application cursor keys
+    /// mode (DECCKM) before the first read, instead of keeping the one left by
+    /// the last program. Arrows are decoded in both modes, a set mode is reset on drop
+    pub fn application_cursor_keys(mut self, enable: bool) -> Self {
+        self.cursor_keys_mode = Some(enable);
+        self
+    }
+
+    /// Send the DECCKM mode asked for with `application_cursor_keys`, once
+    fn send_cursor_keys_mode(&mut self) -> Result<()> {
+        if let Some(enable) = self.cursor_keys_mode.take() {
+            let mode: &[u8] = match enable {
+                true => b"\x1b[?1h",
+                false => b"\x1b[?1l",
+            };
+            write_terminal(self.tty.as_ref(), mode)?;
+            self.application_cursor_keys = enable;
+        }
+        Ok(())
+    }
+
+    /// Send `query` to the terminal & wait up to `timeout` for its answer.
+    /// The keys pressed in the meantime are kept for the next iterations
+    pub fn query(&mut self, query: Query, timeout: Duration) -> Result<Option<Response>> {
+        let termios_enviroment: termios = setup_raw_mode_fd(self.fd(), true)?;
+        let answer = self.wait_response(query, timeout);
+        restore_fd(self.fd(), &termios_enviroment)?;
+        answer
+    }
+
+    /// Position of the cursor as (row, column), starting at 1. `None` if the
+    /// terminal doesn't answer DECXCPR, see `Query::CursorPosition`
+    pub fn cursor_position(&mut self, timeout: Duration) -> Result<Option<(u16, u16)>> {
+        match self.query(Query::CursorPosition, timeout)? {
+            Some(Response::CursorPosition { row, column }) => Ok(Some((row, column))),
+            _ => Ok(None),
+        }
+    }
+
+    /// True if the terminal answers the kitty keyboard protocol query
+    pub fn supports_kitty_keyboard(&mut self, timeout: Duration) -> Result<bool> {
+        Ok(self.query(Query::KittyKeyboard, timeout)?.is_some())
+    }
+
+    /// True if the terminal knows the synchronized output mode (`CSI ? 2026 h`)
+    pub fn supports_synchronized_output(&mut self, timeout: Duration) -> Result<bool> {
+        match self.query(Query::Mode(SYNCHRONIZED_OUTPUT), timeout)? {
+            Some(Response::Mode { value, .. }) => Ok(matches!(value, 1..=3)),
+            _ => Ok(false),
+        }
+    }
+
+    /// Write the query & read until its response, the sentinel or the timeout,
+    /// leaving the other bytes in `pending`
+    fn wait_response(&mut self, query: Query, timeout: Duration) -> Result<Option<Response>> {
+        let deadline = Instant::now() + timeout;
+        let sentinel = query.needs_sentinel();
+        let mut request = query.request();
+        if sentinel {
+            request.extend_from_slice(&Query::PrimaryDeviceAttributes.request());
+        }
+        write_terminal(self.tty.as_ref(), &request)?;
+
+        let mut answer: Option<Response> = None;
+        loop {
+            let mut from = 0;
+            while let Some((response, start, end)) = find_response(&self.pending[from..]) {
+                let (start, end) = (from + start, from + end);
+                if answer.is_none() && query.matches(&response) {
+                    self.pending.drain(start..end);
+                    if !sentinel {
+                        return Ok(Some(response));
+                    }
+                    answer = Some(response);
+                    from = start;
+                } else if sentinel && matches!(response, Response::PrimaryDeviceAttributes(_)) {
+                    self.pending.drain(start..end);
+                    return Ok(answer);
+                } else {
+                    from = end;
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(answer);
+            }
+            let mut buffer: [u8; READ_BYTES] = [0; READ_BYTES];
+            let read = read_terminal(self.tty.as_ref(), &mut buffer)?;
+            self.pending.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    /// Read the bytes available on the terminal, already in raw mode,
+    /// for `pending_event`. 0 means the input is closed. The fd is read
+    /// directly, as bytes left in the stdin buffer would never wake `poll()`
+    pub(crate) fn fill(&mut self) -> Result<usize> {
+        let mut buffer: [u8; READ_BYTES] = [0; READ_BYTES];
+        let read = unsafe { read(self.fd(), buffer.as_mut_ptr().cast(), READ_BYTES) };
+        if read < 0 {
+            return Err(Error::last_os_error());
+        }
+        let read = read as usize;
+        self.read_time = Instant::now();
+        self.pending.extend_from_slice(&buffer[..read]);
+        Ok(read)
+    }
+
+    /// Next event in the bytes already read, without waiting
+    pub(crate) fn pending_event(&mut self) -> Option<KeyEvent> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let (key, used) = decode(&self.pending, self.modify_other_keys);
+        self.pending.drain(..used);
+        Some(self.stamp(key))
+    }
+
+    /// Turn modifyOtherKeys on or off, if the keyboard uses it. Turning it on
+    /// before reading also sends the DECCKM mode the first time
+    pub(crate) fn set_modify_other_keys(&mut self, enable: bool) -> Result<()> {
+        if enable {
+            self.send_cursor_keys_mode()?;
+        }
+        match (self.modify_other_keys, enable) {
+            (false, _) => Ok(()),
+            (true, true) => write_terminal(self.tty.as_ref(), MODIFY_OTHER_KEYS_ON),
+            (true, false) => write_terminal(self.tty.as_ref(), MODIFY_OTHER_KEYS_OFF),
+        }
+    }
+
+    /// Wait for the next key, keeping the bytes of the read that
+    /// belong to the following keys
+    fn read_key(&mut self) -> Keys {
+        if self.pending.is_empty() {
+            if let Err(err) = self.send_cursor_keys_mode() {
+                eprintln!("Error: {}", err);
+            }
+            let mut buffer: [u8; READ_BYTES] = [0; READ_BYTES];
+            let read = read_raw(&mut buffer, self.modify_other_keys, self.tty.as_ref());
+            self.read_time = Instant::now();
+            #[cfg(any(
+                feature = "ctrl_lower_letter",
+                feature = "ctrl_upper_letter",
+                feature = "full"
+            ))]
+            if let Some(key) = take_ctrl_c() {
+                return key;
+            }
+            self.pending.extend_from_slice(&buffer[..read]);
+        }
+        let (key, used) = decode(&self.pending, self.modify_other_keys);
+        self.pending.drain(..used);
+        key
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        if self.application_cursor_keys {
+            let _ = write_terminal(self.tty.as_ref(), b"\x1b[?1l");
+        }
     }
 }
 
 impl Iterator for Keyboard {
     type Item = Keys;
     fn next(&mut self) -> Option<Keys> {
-        Some(get_key_from_keyboard())
+        Some(self.read_key())
     }
 }
 
 /// Get the key press from the keyboard looking the
 /// hex data in the I/O termios
 pub fn get_key_from_keyboard() -> Keys {
+    let mut pending = PENDING.lock().unwrap_or_else(PoisonError::into_inner);
+    let key = next_key(&mut pending, |buffer| read_raw(buffer, false, None));
+    #[cfg(any(
+        feature = "ctrl_lower_letter",
+        feature = "ctrl_upper_letter",
+        feature = "full"
+    ))]
+    if let Some(key) = take_ctrl_c() {
+        pending.clear();
+        return key;
+    }
+    key
+}
+
+/// Decode the next key of `pending`, filling it with `read` first when it's
+/// empty. The reads take up to `READ_BYTES`, so sequences come whole
+fn next_key(pending: &mut Vec<u8>, read: impl FnOnce(&mut [u8]) -> usize) -> Keys {
+    if pending.is_empty() {
+        let mut buffer: [u8; READ_BYTES] = [0; READ_BYTES];
+        let read = read(&mut buffer);
+        pending.extend_from_slice(&buffer[..read]);
+    }
+    let (key, used) = decode(pending, false);
+    pending.drain(..used);
+    key
+}
+
+/// Setup the raw mode, wait for the bytes in the I/O termios & restore it
+fn read_raw(buffer: &mut [u8], modify_other_keys: bool, tty: Option<&File>) -> usize {
     #[cfg(any(
         feature = "ctrl_lower_letter",
         feature = "ctrl_upper_letter",
         feature = "full"
     ))]
     unsafe {
-        signal(SIGINT, sig_handler as usize)
+        signal(SIGINT, sig_handler as *const () as usize)
     };
 
-    let termios_enviroment: termios = setup_raw_mode().unwrap();
+    let fd = tty.map_or(0, AsRawFd::as_raw_fd);
+    let termios_enviroment: termios = setup_raw_mode_fd(fd, true).unwrap();
+    if modify_other_keys {
+        write_terminal(tty, MODIFY_OTHER_KEYS_ON).expect("Error enabling modifyOtherKeys");
+    }
     stdout().flush().unwrap();
+    let read = match read_terminal(tty, buffer) {
+        Ok(read) => read,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            0
+        }
+    };
+    if modify_other_keys {
+        write_terminal(tty, MODIFY_OTHER_KEYS_OFF).expect("Error disabling modifyOtherKeys");
+    }
+    stdout().flush().unwrap();
+    restore_fd(fd, &termios_enviroment).expect("Error with termios restore");
+
+    #[cfg(any(
+        feature = "ctrl_lower_letter",
+        feature = "ctrl_upper_letter",
+        feature = "full"
+    ))]
+    unsafe {
+        signal(SIGINT, 0)
+    };
+
+    read
+}
+
+/// Read from the tty if any, else from stdin. Stdin is read on its fd, not
+/// through `io::stdin()`, so no bytes stay buffered away from `Keyboard::wait`
+fn read_terminal(tty: Option<&File>, buffer: &mut [u8]) -> Result<usize> {
+    if let Some(mut tty) = tty {
+        return tty.read(buffer);
+    }
+    let read = unsafe { read(0, buffer.as_mut_ptr().cast(), buffer.len()) };
+    if read < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(read as usize)
+}
+
+/// Write to the tty if any, else to stdout
+fn write_terminal(tty: Option<&File>, bytes: &[u8]) -> Result<()> {
+    match tty {
+        Some(mut tty) => tty.write_all(bytes),
+        None => {
+            let mut stdout = stdout();
+            stdout.write_all(bytes)?;
+            stdout.flush()
+        }
+    }
+}
+
+/// If a CTRL+C signal arrived, reset `CTRL_C` & get the key
+#[cfg(any(
+    feature = "ctrl_lower_letter",
+    feature = "ctrl_upper_letter",
+    feature = "full"
+))]
+fn take_ctrl_c() -> Option<Keys> {
+    let mut ctrl_c_handler = CTRL_C.lock().unwrap();
+    if !*ctrl_c_handler {
+        return None;
+    }
+    *ctrl_c_handler = false;
+    if cfg!(any(feature = "ctrl_lower_letter", feature = "full")) {
+        Some(Keys::Ctrl('c'))
+    } else {
+        Some(Keys::Ctrl('C'))
+    }
+}
+
+/// Decode the first key in `bytes`, returning it & the amount of bytes it takes
+pub(crate) fn decode(bytes: &[u8], modify_other_keys: bool) -> (Keys, usize) {
+    if modify_other_keys {
+        if let Some(found) = decode_modify_other_keys(bytes) {
+            return found;
+        }
+    }
+    if let Some(found) = decode_modified_csi(bytes) {
+        return found;
+    }
+    let used = chunk_len(bytes);
+    let key = match_tables(&bytes[..used]);
+    if key != Keys::Null || used == 0 {
+        return (key, used);
+    }
+    let Some(end) = escape_sequence_end(bytes) else {
+        return (Keys::Unknown(RawBytes::new(&bytes[..used])), used);
+    };
+    // F5..F12 are kept in the `f` table by their tail, like `5 ~` for `ESC [ 1 5 ~`
+    if let [0x1B, b'[', tens, units, b'~'] = bytes[..end] {
+        if matches!(parse_number(&[tens, units]), Some(15 | 17..=21 | 23 | 24)) {
+            let key = match_tables(&bytes[3..end]);
+            if key != Keys::Null {
+                return (key, end);
+            }
+        }
+    }
+    (Keys::Unknown(RawBytes::new(&bytes[..end])), end)
+}
+
+/// Bytes looked up in the tables: the start of an escape sequence up to
+/// `BYTES`, ESC & the char after it (Alt + char) or a single UTF-8 char,
+/// so a read with many keys gives them one by one
+fn chunk_len(bytes: &[u8]) -> usize {
+    match bytes {
+        [] => 0,
+        [0x1B, b'[' | b'O', ..] => bytes.len().min(BYTES),
+        [0x1B] | [0x1B, 0x1B, ..] => 1,
+        [0x1B, rest @ ..] => 1 + char_len(rest),
+        _ => char_len(bytes),
+    }
+}
+
+/// Bytes of the UTF-8 char at the start of `bytes`, 1 for a byte that
+/// starts no char & less for a char cut by the end of the read
+fn char_len(bytes: &[u8]) -> usize {
+    let len = match bytes[0] {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return 1,
+    };
+    (1..len.min(bytes.len()))
+        .find(|&index| bytes[index] & 0xC0 != 0x80)
+        .unwrap_or(len.min(bytes.len()))
+}
+
+/// Length of the whole CSI (`ESC [ .. final`) or SS3 (`ESC O x`) sequence at the start of `bytes`
+fn escape_sequence_end(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [0x1B, b'[', rest @ ..] => rest
+            .iter()
+            .position(|byte| (0x40..=0x7E).contains(byte))
+            .map(|position| position + 3),
+        [0x1B, b'O', _, ..] => Some(3),
+        _ => None,
+    }
+}
+
+/// Decode the xterm `modifyOtherKeys` report `CSI 27 ; mod ; code ~`
+fn decode_modify_other_keys(bytes: &[u8]) -> Option<(Keys, usize)> {
+    let rest = bytes.strip_prefix(b"\x1b[27;")?;
+    let end = rest.iter().position(|&byte| byte == b'~')?;
+    let mut params = rest[..end].split(|&byte| byte == b';').map(parse_number);
+    let modifiers = Modifiers::from_xterm(params.next()??);
+    let code = match params.next()?? {
+        0x0D => KeyCode::Enter,
+        0x09 => KeyCode::Tab,
+        0x1B => KeyCode::Escape,
+        0x20 => KeyCode::Space,
+        0x08 | 0x7F => KeyCode::Delete,
+        code => KeyCode::Char(char::from_u32(code)?),
+    };
+    let used = b"\x1b[27;".len() + end + 1;
+    Some((Keys::with_modifiers(code, modifiers), used))
+}
+
+/// Decode the xterm keys with modifiers: `CSI 1 ; mod x` for the arrows,
+/// Home, End & F1..F4 & `CSI number ; mod ~` for F5..F12
+fn decode_modified_csi(bytes: &[u8]) -> Option<(Keys, usize)> {
+    let end = escape_sequence_end(bytes)?;
+    if bytes[1] != b'[' {
+        return None;
+    }
+    let mut params = bytes[2..end - 1]
+        .split(|&byte| byte == b';')
+        .map(parse_number);
+    let (number, modifiers) = (params.next()??, params.next()??);
+    if params.next().is_some() {
+        return None;
+    }
+    let code = match (number, bytes[end - 1]) {
+        (1, b'A') => KeyCode::Up,
+        (1, b'B') => KeyCode::Down,
+        (1, b'C') => KeyCode::Right,
+        (1, b'D') => KeyCode::Left,
+        (1, b'H') => KeyCode::Home,
+        (1, b'F') => KeyCode::End,
+        (1, final_byte @ b'P'..=b'S') => KeyCode::F(final_byte - b'P' + 1),
+        (number, b'~') => match number {
+            15 => KeyCode::F(5),
+            17..=21 => KeyCode::F(number as u8 - 11),
+            23 | 24 => KeyCode::F(number as u8 - 12),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((
+        Keys::with_modifiers(code, Modifiers::from_xterm(modifiers)),
+        end,
+    ))
+}
+
+/// Parse a decimal parameter of a control sequence
+fn parse_number(bytes: &[u8]) -> Option<u32> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// Look the key in all the enabled tables, `Keys::Null` if no one has it
+fn match_tables(bytes: &[u8]) -> Keys {
+    if bytes.len() > BYTES {
+        return Keys::Null;
+    }
     let mut buffer: [u8; BYTES] = [0; BYTES];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    let buffer = &buffer;
     let mut key: Keys = Keys::Null;
-    match stdin().read(&mut buffer) {
-        Ok(_) => (),
-        Err(err) => eprintln!("Error: {}", err),
-    }
 
     for &(ref pattern, keys) in ARROWS_ENTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
+            key = keys;
+        }
+    }
+
+    for &(ref pattern, keys) in APPLICATION_CURSOR.iter() {
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "standar", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::STANDAR.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "numbers", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::NUMBERS.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "lower_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::LOWER_LETTERS.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "upper_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::UPPER_LETTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "f", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::F.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "ctrl_lower_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::CTRL_LOWER_LETTER.iter() {
-        let mut ctrl_c_handler = CTRL_C.lock().unwrap();
-        if *ctrl_c_handler {
-            *ctrl_c_handler = false;
-            restore(&termios_enviroment).expect("Error with termios restore");
-            return Keys::Ctrl('c');
-        }
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "ctrl_upper_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::CTRL_UPPER_LETTER.iter() {
-        let mut ctrl_c_handler = CTRL_C.lock().unwrap();
-        if *ctrl_c_handler {
-            *ctrl_c_handler = false;
-            restore(&termios_enviroment).expect("Error with termios restore");
-            return Keys::Ctrl('C');
-        }
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
@@ -168,78 +658,178 @@ pub fn get_key_from_keyboard() -> Keys {
     #[cfg(any(feature = "ctrl_standar", feature = "full",))]
     #[cfg(not(feature = "standar"))]
     for &(ref pattern, keys) in crate::keys::CTRL_STANDAR.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "ctrl_numbers", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::CTRL_NUMBERS.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_lower_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_LOWER_LETTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_upper_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_UPPER_LETTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_numbers", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_NUMBERS.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_gr_lower_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_GR_LOWER_LETTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_gr_upper_letter", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_GR_UPPER_LETTER.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
     #[cfg(any(feature = "alt_gr_numbers", feature = "full"))]
     for &(ref pattern, keys) in crate::keys::ALT_GR_NUMBERS.iter() {
-        if buffer == *pattern {
-            restore(&termios_enviroment).expect("Error with termios restore");
+        if buffer == pattern {
             key = keys;
         }
     }
 
-    restore(&termios_enviroment).expect("Error with termios restore");
+    key
+}
 
-    #[cfg(any(
-        feature = "ctrl_lower_letter",
-        feature = "ctrl_upper_letter",
-        feature = "full"
-    ))]
-    unsafe {
-        signal(SIGINT, 0)
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    key
+    /// Keys of `bytes` as if they all came in one read
+    fn decode_all(bytes: &[u8]) -> Vec<Keys> {
+        let mut keys = Vec::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (key, used) = decode(rest, false);
+            assert!(used > 0, "no bytes used from {:02x?}", rest);
+            keys.push(key);
+            rest = &rest[used..];
+        }
+        keys
+    }
+
+    fn unknown(bytes: &[u8]) -> Keys {
+        Keys::Unknown(RawBytes::new(bytes))
+    }
+
+    #[test]
+    fn many_keys_in_one_read() {
+        let letters = cfg!(any(feature = "lower_letter", feature = "full"));
+        let key = |c: char| match letters {
+            true => Keys::Char(c),
+            false => unknown(c.to_string().as_bytes()),
+        };
+        assert_eq!(decode_all(b"aa"), [key('a'); 2]);
+        assert_eq!(decode_all(b"aaaa"), [key('a'); 4]);
+        assert_eq!(decode_all("ñn".as_bytes()), [key('ñ'), key('n')]);
+        assert_eq!(
+            decode_all("a\x1b[Ab\n".as_bytes()),
+            [key('a'), Keys::Up, key('b'), Keys::Enter]
+        );
+        assert_eq!(decode_all("€€".as_bytes()).len(), 2);
+        let (escape, _) = decode(b"\x1b", false);
+        assert_eq!(decode_all(b"\x1b\x1b"), [escape; 2]);
+    }
+
+    #[test]
+    fn alt_keys_in_one_read() {
+        let key = match cfg!(any(feature = "alt_lower_letter", feature = "full")) {
+            true => Keys::Alt('x'),
+            false => unknown(b"\x1bx"),
+        };
+        assert_eq!(decode_all(b"\x1bx\x1bx"), [key; 2]);
+    }
+
+    #[test]
+    fn home_end_in_both_forms() {
+        for (csi, ss3, key) in [
+            (b"\x1b[H", b"\x1bOH", Keys::Home),
+            (b"\x1b[F", b"\x1bOF", Keys::End),
+        ] {
+            assert_eq!(decode_all(csi), [key]);
+            assert_eq!(decode_all(ss3), [key]);
+        }
+    }
+
+    #[test]
+    fn csi_tilde_keys() {
+        for number in 1..=6 {
+            let sequence = format!("\x1b[{}~", number);
+            let bytes = sequence.as_bytes();
+            assert_eq!(decode_all(bytes), [unknown(bytes)], "{:?}", sequence);
+            let twice = [bytes, bytes].concat();
+            assert_eq!(decode_all(&twice), [unknown(bytes); 2]);
+        }
+        let f_keys = cfg!(any(feature = "f", feature = "full"));
+        for (number, f) in [
+            (15, 5),
+            (17, 6),
+            (18, 7),
+            (19, 8),
+            (20, 9),
+            (21, 10),
+            (23, 11),
+            (24, 12),
+        ] {
+            let sequence = format!("\x1b[{}~", number);
+            let bytes = sequence.as_bytes();
+            let key = match f_keys {
+                true => Keys::F(f),
+                false => unknown(bytes),
+            };
+            assert_eq!(decode_all(bytes), [key], "{:?}", sequence);
+        }
+        for sequence in [
+            "\x1b[16~",
+            "\x1b[22~",
+            "\x1b[25~",
+            "\x1b[200~",
+            "\x1b[3;5;1~",
+        ] {
+            let bytes = sequence.as_bytes();
+            assert_eq!(decode_all(bytes), [unknown(bytes)], "{:?}", sequence);
+        }
+    }
+
+    /// The F keys written at once by the terminal are read whole, not cut in
+    /// 3 byte reads (`ESC [ 1` & `5~`), & the bytes of the next keys are kept
+    #[test]
+    fn f_keys_in_one_read() {
+        let f_keys = cfg!(any(feature = "f", feature = "full"));
+        let key = |bytes: &[u8], number| match f_keys {
+            true => Keys::F(number),
+            false => unknown(bytes),
+        };
+        let mut input: &[u8] = b"\x1b[15~\x1b[24~";
+        let mut pending = Vec::new();
+        let first = next_key(&mut pending, |buffer| input.read(buffer).unwrap());
+        let second = next_key(&mut pending, |_| panic!("read again"));
+        assert_eq!(first, key(b"\x1b[15~", 5));
+        assert_eq!(second, key(b"\x1b[24~", 12));
+        assert!(pending.is_empty());
+    }
 }
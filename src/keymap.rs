@@ -0,0 +1,349 @@
+/***************************************************************************************
+ *   keymap.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{event::KeyEvent, keys::Keys};
+use std::time::{Duration, Instant};
+
+/// Default max time between the keys of a sequence
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Tree of key sequences (`g g`, `Ctrl+X Ctrl+S`) bound to actions
+#[derive(Debug, Clone)]
+pub struct Keymap<A> {
+    entries: Vec<(Keys, Node<A>)>,
+}
+
+/// Key of a keymap: the action of the sequence ending here &
+/// the longer sequences that go on from it
+#[derive(Debug, Clone)]
+struct Node<A> {
+    action: Option<A>,
+    next: Keymap<A>,
+}
+
+impl<A> Default for Keymap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> Keymap<A> {
+    /// Get an empty keymap
+    pub fn new() -> Self {
+        Keymap {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Bind `sequence` to `action`, replacing the previous action of that sequence
+    pub fn bind(&mut self, sequence: &[Keys], action: A) -> &mut Self {
+        if let Some((&first, rest)) = sequence.split_first() {
+            let node = self.node_mut(first);
+            if rest.is_empty() {
+                node.action = Some(action);
+            } else {
+                node.next.bind(rest, action);
+            }
+        }
+        self
+    }
+
+    /// Nest `keymap` under `prefix`, like the `Ctrl+X` map of Emacs.
+    /// Its bindings replace the ones already under the prefix
+    pub fn bind_prefix(&mut self, prefix: &[Keys], keymap: Keymap<A>) -> &mut Self {
+        match prefix.split_first() {
+            None => {
+                for (key, node) in keymap.entries {
+                    self.merge(key, node);
+                }
+            }
+            Some((&first, rest)) => {
+                self.node_mut(first).next.bind_prefix(rest, keymap);
+            }
+        }
+        self
+    }
+
+    /// Remove the action bound to `sequence`, keeping the longer sequences
+    pub fn unbind(&mut self, sequence: &[Keys]) -> Option<A> {
+        let (first, rest) = sequence.split_first()?;
+        let node = self.get_mut(*first)?;
+        if rest.is_empty() {
+            node.action.take()
+        } else {
+            node.next.unbind(rest)
+        }
+    }
+
+    /// Action bound to exactly `sequence`
+    pub fn get(&self, sequence: &[Keys]) -> Option<&A> {
+        self.node(sequence)?.action.as_ref()
+    }
+
+    /// True if longer sequences start with `sequence`
+    pub fn is_prefix(&self, sequence: &[Keys]) -> bool {
+        self.node(sequence)
+            .is_some_and(|node| !node.next.entries.is_empty())
+    }
+
+    /// All the bound sequences with their actions
+    pub fn bindings(&self) -> Vec<(Vec<Keys>, &A)> {
+        let mut bindings = Vec::new();
+        self.collect(&mut Vec::new(), &mut bindings);
+        bindings
+    }
+
+    fn collect<'a>(&'a self, prefix: &mut Vec<Keys>, bindings: &mut Vec<(Vec<Keys>, &'a A)>) {
+        for (key, node) in &self.entries {
+            prefix.push(*key);
+            if let Some(action) = &node.action {
+                bindings.push((prefix.clone(), action));
+            }
+            node.next.collect(prefix, bindings);
+            prefix.pop();
+        }
+    }
+
+    fn node(&self, sequence: &[Keys]) -> Option<&Node<A>> {
+        let (first, rest) = sequence.split_first()?;
+        let node = self
+            .entries
+            .iter()
+            .find(|(key, _)| key == first)
+            .map(|(_, node)| node)?;
+        if rest.is_empty() {
+            Some(node)
+        } else {
+            node.next.node(rest)
+        }
+    }
+
+    fn get_mut(&mut self, key: Keys) -> Option<&mut Node<A>> {
+        self.entries
+            .iter_mut()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, node)| node)
+    }
+
+    fn node_mut(&mut self, key: Keys) -> &mut Node<A> {
+        let index = match self.entries.iter().position(|(bound, _)| *bound == key) {
+            Some(index) => index,
+            None => {
+                self.entries.push((
+                    key,
+                    Node {
+                        action: None,
+                        next: Keymap::new(),
+                    },
+                ));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index].1
+    }
+
+    fn merge(&mut self, key: Keys, node: Node<A>) {
+        let target = self.node_mut(key);
+        if node.action.is_some() {
+            target.action = node.action;
+        }
+        for (key, node) in node.next.entries {
+            target.next.merge(key, node);
+        }
+    }
+}
+
+/// Result of feeding a key to the `SequenceMatcher`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Match<A> {
+    /// The keys so far start a longer sequence, wait for the next one
+    Pending,
+    /// A whole sequence was typed
+    Matched(A),
+    /// The keys typed are not bound, given back to handle them another way
+    NoMatch(Vec<Keys>),
+}
+
+/// Follows the key stream, matching it against a `Keymap`
+#[derive(Debug, Clone)]
+pub struct SequenceMatcher<A> {
+    keymap: Keymap<A>,
+    timeout: Duration,
+    buffer: Vec<Keys>,
+    last_key: Option<Instant>,
+}
+
+impl<A: Clone> SequenceMatcher<A> {
+    /// Get a matcher over `keymap` with a timeout of 1 second between keys
+    pub fn new(keymap: Keymap<A>) -> Self {
+        SequenceMatcher {
+            keymap,
+            timeout: SEQUENCE_TIMEOUT,
+            buffer: Vec::new(),
+            last_key: None,
+        }
+    }
+
+    /// Max time between the keys of a sequence
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The keymap, to change the bindings
+    pub fn keymap_mut(&mut self) -> &mut Keymap<A> {
+        &mut self.keymap
+    }
+
+    /// Keys typed of a sequence not completed yet
+    pub fn pending(&self) -> &[Keys] {
+        &self.buffer
+    }
+
+    /// Drop the keys typed of a sequence not completed yet
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.last_key = None;
+    }
+
+    /// Feed the key read now, see `feed_at`
+    pub fn feed(&mut self, key: Keys) -> Vec<Match<A>> {
+        self.feed_at(key, Instant::now())
+    }
+
+    /// Feed a key event, using the time it arrived
+    pub fn feed_event(&mut self, event: &KeyEvent) -> Vec<Match<A>> {
+        self.feed_at(event.key, event.time)
+    }
+
+    /// Feed `key`, read at `time`, getting all its results in order: a pending
+    /// sequence whose time ran out gives its own action when it has one, or
+    /// `NoMatch` with its keys, then the key gives one or two more (`Matched`
+    /// for a sequence before it, then its own). `Keys::Null`, that the keyboard
+    /// yields while no key is pressed, only checks the timeout
+    pub fn feed_at(&mut self, key: Keys, time: Instant) -> Vec<Match<A>> {
+        let mut results = Vec::new();
+        if let Some(last_key) = self.last_key {
+            if time.saturating_duration_since(last_key) > self.timeout {
+                results.push(self.flush());
+            }
+        }
+        if key != Keys::Null {
+            self.push(key, time, &mut results);
+        }
+        results
+    }
+
+    /// Add `key` to the sequence being typed, keeping the results in `results`
+    fn push(&mut self, key: Keys, time: Instant, results: &mut Vec<Match<A>>) {
+        self.buffer.push(key);
+        if self.keymap.is_prefix(&self.buffer) {
+            self.last_key = Some(time);
+            results.push(Match::Pending);
+            return;
+        }
+        if let Some(action) = self.keymap.get(&self.buffer).cloned() {
+            self.reset();
+            results.push(Match::Matched(action));
+            return;
+        }
+        let typed = &self.buffer[..self.buffer.len() - 1];
+        if let Some(action) = self.keymap.get(typed).cloned() {
+            // the sequence before the key was bound too, so it runs & the key starts again
+            self.reset();
+            results.push(Match::Matched(action));
+            self.push(key, time, results);
+            return;
+        }
+        let keys = std::mem::take(&mut self.buffer);
+        self.reset();
+        results.push(Match::NoMatch(keys));
+    }
+
+    /// Resolve the pending sequence because its time ran out
+    fn flush(&mut self) -> Match<A> {
+        let result = match self.keymap.get(&self.buffer).cloned() {
+            Some(action) => Match::Matched(action),
+            None => Match::NoMatch(self.buffer.clone()),
+        };
+        self.reset();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher() -> SequenceMatcher<&'static str> {
+        let mut keymap = Keymap::new();
+        keymap
+            .bind(&[Keys::Char('g'), Keys::Char('g')], "top")
+            .bind(&[Keys::Char('x')], "del")
+            .bind(&[Keys::Char('d')], "down")
+            .bind(&[Keys::Char('d'), Keys::Char('d')], "line");
+        SequenceMatcher::new(keymap)
+    }
+
+    #[test]
+    fn results_of_the_same_key() {
+        let mut matcher = matcher();
+        let start = Instant::now();
+        assert_eq!(matcher.feed_at(Keys::Char('g'), start), [Match::Pending]);
+        let later = start + Duration::from_secs(2);
+        assert_eq!(
+            matcher.feed_at(Keys::Char('x'), later),
+            [Match::NoMatch(vec![Keys::Char('g')]), Match::Matched("del")]
+        );
+        assert_eq!(matcher.feed_at(Keys::Null, later), []);
+    }
+
+    #[test]
+    fn bound_prefix_then_key() {
+        let mut matcher = matcher();
+        let start = Instant::now();
+        assert_eq!(matcher.feed_at(Keys::Char('d'), start), [Match::Pending]);
+        assert_eq!(
+            matcher.feed_at(Keys::Char('x'), start),
+            [Match::Matched("down"), Match::Matched("del")]
+        );
+        assert_eq!(matcher.feed_at(Keys::Char('d'), start), [Match::Pending]);
+        assert_eq!(
+            matcher.feed_at(Keys::Char('d'), start),
+            [Match::Matched("line")]
+        );
+    }
+
+    #[test]
+    fn null_checks_the_timeout() {
+        let mut matcher = matcher();
+        let start = Instant::now();
+        assert_eq!(matcher.feed_at(Keys::Null, start), []);
+        assert_eq!(matcher.feed_at(Keys::Char('d'), start), [Match::Pending]);
+        assert_eq!(matcher.feed_at(Keys::Null, start), []);
+        let later = start + Duration::from_secs(2);
+        assert_eq!(matcher.feed_at(Keys::Null, later), [Match::Matched("down")]);
+        assert_eq!(matcher.feed_at(Keys::Null, later), []);
+        assert_eq!(
+            matcher.feed_at(Keys::Char('q'), later),
+            [Match::NoMatch(vec![Keys::Char('q')])]
+        );
+    }
+}
@@ -18,11 +18,14 @@
  *                                                                                     *
  **************************************************************************************/
 
+use std::{fmt, str::FromStr};
+
 /// Bytes taken to look what key is pressed
 pub const BYTES: usize = 3;
 
-/// Keys enum
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Keys enum. New keys can come in minor versions, so matches need a `_` arm
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum Keys {
     Up,
     Down,
@@ -41,24 +44,535 @@ pub enum Keys {
     Ctrl(char),
     Alt(char),
     AltGr(char),
+    /// Any key + a combination of modifiers that has no variant of its own,
+    /// like `Shift + Enter` or `Ctrl + Alt + a`
+    Modified(Modifiers, KeyCode),
+    /// Bytes read that no enabled table knows: an unsupported key,
+    /// a terminal response or a part of a longer sequence
+    Unknown(RawBytes),
+    /// No key arrived
     Null,
 }
 
-/// Default keys
-pub const ARROWS_ENTER: [([u8; BYTES], Keys); 5] = [
+/// Max bytes kept by `Keys::Unknown`
+pub const RAW_BYTES: usize = 16;
+
+/// Bytes of an unknown key, inline so `Keys` keeps being `Copy`.
+/// Longer sequences are cut to `RAW_BYTES`
+#[derive(PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct RawBytes {
+    len: u8,
+    bytes: [u8; RAW_BYTES],
+}
+
+impl RawBytes {
+    /// Keep the first `RAW_BYTES` of `bytes`
+    pub fn new(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(RAW_BYTES);
+        let mut raw = RawBytes {
+            len: len as u8,
+            bytes: [0; RAW_BYTES],
+        };
+        raw.bytes[..len].copy_from_slice(&bytes[..len]);
+        raw
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl fmt::Debug for RawBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x?}", self.as_bytes())
+    }
+}
+
+/// Key without modifiers, carried by `Keys::Modified`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum KeyCode {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Home,
+    Tab,
+    Space,
+    Delete,
+    Escape,
+    End,
+    Char(char),
+    F(u8),
+}
+
+impl From<KeyCode> for Keys {
+    fn from(code: KeyCode) -> Self {
+        Keys::from_code(code)
+    }
+}
+
+/// Modifiers held down with a key (Shift, Alt & Ctrl)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash, PartialOrd, Ord)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const SHIFT: Modifiers = Modifiers(0b001);
+    pub const ALT: Modifiers = Modifiers(0b010);
+    pub const CTRL: Modifiers = Modifiers(0b100);
+
+    /// Get the modifiers from the xterm parameter (`1 + bitmask`),
+    /// Meta is taken as Alt
+    pub fn from_xterm(param: u32) -> Self {
+        let mask = param.saturating_sub(1) as u8;
+        Modifiers((mask & 0b111) | ((mask & 0b1000) >> 2))
+    }
+
+    /// Get the xterm parameter (`1 + bitmask`) of these modifiers
+    pub fn to_xterm(self) -> u32 {
+        self.0 as u32 + 1
+    }
+
+    /// True if all the modifiers in `other` are held
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// True if no modifier is held
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The same modifiers without the ones in `other`
+    pub fn without(self, other: Modifiers) -> Self {
+        Modifiers(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Modifiers;
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+impl Keys {
+    /// Build the key from a key code & the modifiers held down, using
+    /// `Ctrl(_)`, `Alt(_)` & `Backtab` when they fit & `Modified` for the rest.
+    /// Shift is dropped for chars, since the char is already the shifted one
+    pub fn with_modifiers(code: KeyCode, modifiers: Modifiers) -> Keys {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers.without(Modifiers::SHIFT),
+            _ => modifiers,
+        };
+        match (code, modifiers) {
+            (_, Modifiers::NONE) => code.into(),
+            (KeyCode::Char(c), Modifiers::CTRL) => Keys::Ctrl(c),
+            (KeyCode::Char(c), Modifiers::ALT) => Keys::Alt(c),
+            (KeyCode::Tab, Modifiers::SHIFT) => Keys::Backtab,
+            _ => Keys::Modified(modifiers, code),
+        }
+    }
+
+    /// Stable numeric identity of the key, to keep it in compact arrays or
+    /// share it across FFI. The layout won't change between versions:
+    ///
+    /// - bits 24..32: variant. `Null` 0, `Up` 1, `Down` 2, `Left` 3, `Right` 4,
+    ///   `Enter` 5, `Home` 6, `Tab` 7, `Backtab` 8, `Space` 9, `Delete` 10,
+    ///   `Escape` 11, `End` 12, `Char` 13, `F` 14, `Ctrl` 15, `Alt` 16, `AltGr` 17.
+    ///   `Modified` is `0x80` + the variant of its key code
+    /// - bits 21..24: modifiers of `Modified`, Shift `0b001`, Alt `0b010`, Ctrl `0b100`
+    /// - bits 0..21: the char (code point) or the F number
+    /// - `Unknown` keys of up to 3 bytes are the variant 18 + their length
+    ///   (18 to 21) with the bytes in bits 0..24, the first one in the highest bits.
+    ///   Longer ones can't be encoded & give `u32::MAX`, that is not a key
+    ///
+    /// So `Keys::Null` is 0 & `Keys::Char('a')` is `0x0D00_0061`
+    pub const fn to_u32(self) -> u32 {
+        let (variant, payload) = match self {
+            Keys::Null => (0, 0),
+            Keys::Up => (1, 0),
+            Keys::Down => (2, 0),
+            Keys::Left => (3, 0),
+            Keys::Right => (4, 0),
+            Keys::Enter => (5, 0),
+            Keys::Home => (6, 0),
+            Keys::Tab => (7, 0),
+            Keys::Backtab => (8, 0),
+            Keys::Space => (9, 0),
+            Keys::Delete => (10, 0),
+            Keys::Escape => (11, 0),
+            Keys::End => (12, 0),
+            Keys::Char(c) => (13, c as u32),
+            Keys::F(number) => (14, number as u32),
+            Keys::Ctrl(c) => (15, c as u32),
+            Keys::Alt(c) => (16, c as u32),
+            Keys::AltGr(c) => (17, c as u32),
+            Keys::Modified(modifiers, code) => {
+                let code = Keys::from_code(code).to_u32();
+                return code | 0x8000_0000 | ((modifiers.0 as u32) << 21);
+            }
+            Keys::Unknown(raw) => {
+                if raw.len > 3 {
+                    return u32::MAX;
+                }
+                let mut payload = 0;
+                let mut index = 0;
+                while index < raw.len as usize {
+                    payload |= (raw.bytes[index] as u32) << (16 - 8 * index);
+                    index += 1;
+                }
+                (18 + raw.len as u32, payload)
+            }
+        };
+        (variant << 24) | payload
+    }
+
+    /// Get the key back from `to_u32`, `None` if the number is not a key
+    pub const fn from_u32(value: u32) -> Option<Keys> {
+        let payload = value & 0x1F_FFFF;
+        let modifiers = ((value >> 21) & 0b111) as u8;
+        let variant = value >> 24;
+        if variant & 0x80 != 0 {
+            let code = match Keys::from_u32(value & 0x7F1F_FFFF) {
+                Some(Keys::Up) => KeyCode::Up,
+                Some(Keys::Down) => KeyCode::Down,
+                Some(Keys::Left) => KeyCode::Left,
+                Some(Keys::Right) => KeyCode::Right,
+                Some(Keys::Enter) => KeyCode::Enter,
+                Some(Keys::Home) => KeyCode::Home,
+                Some(Keys::Tab) => KeyCode::Tab,
+                Some(Keys::Space) => KeyCode::Space,
+                Some(Keys::Delete) => KeyCode::Delete,
+                Some(Keys::Escape) => KeyCode::Escape,
+                Some(Keys::End) => KeyCode::End,
+                Some(Keys::Char(c)) => KeyCode::Char(c),
+                Some(Keys::F(number)) => KeyCode::F(number),
+                _ => return None,
+            };
+            return Some(Keys::Modified(Modifiers(modifiers), code));
+        }
+        if matches!(variant, 18..=21) {
+            let len = variant - 18;
+            if value & (0xFF_FFFF >> (8 * len)) != 0 {
+                return None;
+            }
+            let mut raw = RawBytes {
+                len: len as u8,
+                bytes: [0; RAW_BYTES],
+            };
+            let mut index = 0;
+            while index < len as usize {
+                raw.bytes[index] = (value >> (16 - 8 * index)) as u8;
+                index += 1;
+            }
+            return Some(Keys::Unknown(raw));
+        }
+        if modifiers != 0 || (variant < 13 && payload != 0) {
+            return None;
+        }
+        let c = match char::from_u32(payload) {
+            Some(c) => c,
+            None => return None,
+        };
+        Some(match variant {
+            0 => Keys::Null,
+            1 => Keys::Up,
+            2 => Keys::Down,
+            3 => Keys::Left,
+            4 => Keys::Right,
+            5 => Keys::Enter,
+            6 => Keys::Home,
+            7 => Keys::Tab,
+            8 => Keys::Backtab,
+            9 => Keys::Space,
+            10 => Keys::Delete,
+            11 => Keys::Escape,
+            12 => Keys::End,
+            13 => Keys::Char(c),
+            14 if payload <= u8::MAX as u32 => Keys::F(payload as u8),
+            15 => Keys::Ctrl(c),
+            16 => Keys::Alt(c),
+            17 => Keys::AltGr(c),
+            _ => return None,
+        })
+    }
+
+    /// `From<KeyCode>` usable in const fns
+    const fn from_code(code: KeyCode) -> Keys {
+        match code {
+            KeyCode::Up => Keys::Up,
+            KeyCode::Down => Keys::Down,
+            KeyCode::Left => Keys::Left,
+            KeyCode::Right => Keys::Right,
+            KeyCode::Enter => Keys::Enter,
+            KeyCode::Home => Keys::Home,
+            KeyCode::Tab => Keys::Tab,
+            KeyCode::Space => Keys::Space,
+            KeyCode::Delete => Keys::Delete,
+            KeyCode::Escape => Keys::Escape,
+            KeyCode::End => Keys::End,
+            KeyCode::Char(c) => Keys::Char(c),
+            KeyCode::F(n) => Keys::F(n),
+        }
+    }
+}
+
+/// Canonical names: `Ctrl+S`, `Alt+Shift+Left`, `F5`, `Space`, `Esc`.
+/// Letters held with Ctrl, Alt or AltGr are written in upper case, adding
+/// `Shift+` when the key is the upper one (`Ctrl('S')` is `Ctrl+Shift+S`)
+impl fmt::Display for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Keys::Up => f.write_str("Up"),
+            Keys::Down => f.write_str("Down"),
+            Keys::Left => f.write_str("Left"),
+            Keys::Right => f.write_str("Right"),
+            Keys::Enter => f.write_str("Enter"),
+            Keys::Home => f.write_str("Home"),
+            Keys::Tab => f.write_str("Tab"),
+            Keys::Backtab => f.write_str("Shift+Tab"),
+            Keys::Space => f.write_str("Space"),
+            Keys::Delete => f.write_str("Delete"),
+            Keys::Escape => f.write_str("Esc"),
+            Keys::End => f.write_str("End"),
+            Keys::Char(c) => write!(f, "{}", c),
+            Keys::F(number) => write!(f, "F{}", number),
+            Keys::Ctrl(c) => write_combo(f, "", Modifiers::CTRL, KeyCode::Char(c)),
+            Keys::Alt(c) => write_combo(f, "", Modifiers::ALT, KeyCode::Char(c)),
+            Keys::AltGr(c) => write_combo(f, "AltGr+", Modifiers::NONE, KeyCode::Char(c)),
+            Keys::Modified(modifiers, code) => write_combo(f, "", modifiers, code),
+            Keys::Unknown(raw) => {
+                f.write_str("Raw(")?;
+                for byte in raw.as_bytes() {
+                    write!(f, "{:02x}", byte)?;
+                }
+                f.write_str(")")
+            }
+            Keys::Null => f.write_str("Null"),
+        }
+    }
+}
+
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Keys::from(*self))
+    }
+}
+
+/// Write the modifiers in the order Ctrl, Alt, Shift & then the key
+fn write_combo(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    modifiers: Modifiers,
+    code: KeyCode,
+) -> fmt::Result {
+    let (modifiers, code) = match code {
+        KeyCode::Char(c) if has_case(c) && c.is_uppercase() => {
+            (modifiers | Modifiers::SHIFT, KeyCode::Char(c))
+        }
+        KeyCode::Char(c) => (modifiers, KeyCode::Char(to_upper(c))),
+        code => (modifiers, code),
+    };
+    f.write_str(prefix)?;
+    for (modifier, name) in MODIFIER_NAMES {
+        if modifiers.contains(modifier) {
+            write!(f, "{}+", name)?;
+        }
+    }
+    write!(f, "{}", code)
+}
+
+/// Modifiers in the order they are written
+const MODIFIER_NAMES: [(Modifiers, &str); 3] = [
+    (Modifiers::CTRL, "Ctrl"),
+    (Modifiers::ALT, "Alt"),
+    (Modifiers::SHIFT, "Shift"),
+];
+
+/// Error of a key name that can't be parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseKeyError(pub String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+/// Parse the names written by `Display` (case insensitive) & the common aliases:
+/// `ctrl+s`, `C-s`, `M-x`, `^S`, `<CR>`, `<Esc>`, `<C-x>`, `S-Tab`, `Return`, `Backspace`.
+/// Unknown keys are written with their bytes in hex, like `Raw(1b5b31)`
+impl FromStr for Keys {
+    type Err = ParseKeyError;
+    fn from_str(name: &str) -> Result<Keys, ParseKeyError> {
+        parse_name(name).ok_or_else(|| ParseKeyError(String::from(name)))
+    }
+}
+
+fn parse_name(name: &str) -> Option<Keys> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Keys::Char(c));
+    }
+    if let Some(inner) = name
+        .strip_prefix('<')
+        .and_then(|name| name.strip_suffix('>'))
+    {
+        return parse_combo(inner, '-');
+    }
+    if let Some(hex) =
+        strip_prefix_ignore_case(name, "raw(").and_then(|name| name.strip_suffix(')'))
+    {
+        return parse_raw(hex);
+    }
+    if let Some(key) = name.strip_prefix('^') {
+        return parse_combo(&format!("ctrl+{}", key), '+');
+    }
+    parse_combo(name, '+').or_else(|| parse_combo(name, '-'))
+}
+
+/// Parse the hex bytes of `Raw(1b5b31)`
+fn parse_raw(hex: &str) -> Option<Keys> {
+    if !hex.len().is_multiple_of(2) || hex.len() / 2 > RAW_BYTES || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(Keys::Unknown(RawBytes::new(&bytes)))
+}
+
+fn strip_prefix_ignore_case<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    match name.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&name[prefix.len()..]),
+        _ => None,
+    }
+}
+
+/// Parse `modifier<separator>modifier<separator>key`
+fn parse_combo(name: &str, separator: char) -> Option<Keys> {
+    let mut modifiers = Modifiers::NONE;
+    let mut alt_gr = false;
+    let mut key = name;
+    while let Some((modifier, rest)) = key.split_once(separator) {
+        if rest.is_empty() {
+            break;
+        }
+        match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" | "c" => modifiers = modifiers | Modifiers::CTRL,
+            "alt" | "meta" | "m" | "a" => modifiers = modifiers | Modifiers::ALT,
+            "shift" | "s" => modifiers = modifiers | Modifiers::SHIFT,
+            "altgr" => alt_gr = true,
+            _ => return None,
+        }
+        key = rest;
+    }
+    if modifiers.is_empty() && !alt_gr {
+        match key.to_ascii_lowercase().as_str() {
+            "backtab" => return Some(Keys::Backtab),
+            "null" => return Some(Keys::Null),
+            _ => {}
+        }
+    }
+    let code = match parse_key_code(key)? {
+        KeyCode::Char(c) if modifiers.contains(Modifiers::SHIFT) => KeyCode::Char(to_upper(c)),
+        KeyCode::Char(c) if alt_gr || !modifiers.is_empty() => KeyCode::Char(to_lower(c)),
+        code => code,
+    };
+    match (alt_gr, code) {
+        (false, code) => Some(Keys::with_modifiers(code, modifiers)),
+        (true, KeyCode::Char(c)) if modifiers.without(Modifiers::SHIFT).is_empty() => {
+            Some(Keys::AltGr(c))
+        }
+        (true, _) => None,
+    }
+}
+
+/// Parse the key without modifiers
+fn parse_key_code(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match key.to_ascii_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" | "return" | "cr" | "ret" => KeyCode::Enter,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "tab" => KeyCode::Tab,
+        "space" | "spc" => KeyCode::Space,
+        "delete" | "del" | "backspace" | "bs" => KeyCode::Delete,
+        "esc" | "escape" => KeyCode::Escape,
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        "plus" => KeyCode::Char('+'),
+        "minus" => KeyCode::Char('-'),
+        name => match name.strip_prefix('f')?.parse::<u8>() {
+            Ok(number) if number > 0 => KeyCode::F(number),
+            _ => return None,
+        },
+    };
+    Some(code)
+}
+
+/// True if the char has an upper & a lower form
+fn has_case(c: char) -> bool {
+    to_upper(c) != to_lower(c)
+}
+
+fn to_upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => c,
+    }
+}
+
+fn to_lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+/// Default keys: arrows, Home & End in their `ESC [` form & Enter
+pub const ARROWS_ENTER: [([u8; BYTES], Keys); 7] = [
     ([0x1B, 0x5B, 0x41], Keys::Up),
     ([0x1B, 0x5B, 0x42], Keys::Down),
     ([0x1B, 0x5B, 0x43], Keys::Right),
     ([0x1B, 0x5B, 0x44], Keys::Left),
+    ([0x1B, 0x5B, 0x48], Keys::Home),
+    ([0x1B, 0x5B, 0x46], Keys::End),
     ([0x0A, 0x00, 0x00], Keys::Enter),
 ];
 
+/// Arrows, Home & End sent in application cursor keys mode (DECCKM, `ESC O A`..)
+/// & the keypad Enter (`ESC O M`), decoded as the `ESC [` ones
+pub const APPLICATION_CURSOR: [([u8; BYTES], Keys); 7] = [
+    ([0x1B, 0x4F, 0x41], Keys::Up),
+    ([0x1B, 0x4F, 0x42], Keys::Down),
+    ([0x1B, 0x4F, 0x43], Keys::Right),
+    ([0x1B, 0x4F, 0x44], Keys::Left),
+    ([0x1B, 0x4F, 0x48], Keys::Home),
+    ([0x1B, 0x4F, 0x46], Keys::End),
+    ([0x1B, 0x4F, 0x4D], Keys::Enter),
+];
+
 /// All special character keys
 #[cfg(any(feature = "standar", feature = "full"))]
-pub const STANDAR: [([u8; BYTES], Keys); 40] = [
-    ([0x1B, 0x5B, 0x48], Keys::Home),
+pub const STANDAR: [([u8; BYTES], Keys); 38] = [
     ([0x09, 0x00, 0x00], Keys::Tab),
-    ([0x1B, 0x5B, 0x46], Keys::End),
     ([0x1B, 0x5B, 0x5a], Keys::Backtab),
     ([0x1b, 0x00, 0x00], Keys::Escape),
     ([0x20, 0x00, 0x00], Keys::Space),
@@ -130,7 +644,7 @@ pub const LOWER_LETTERS: [([u8; BYTES], Keys); 27] = [
     ([0x6C, 0x00, 0x00], Keys::Char('l')),
     ([0x6D, 0x00, 0x00], Keys::Char('m')),
     ([0x6E, 0x00, 0x00], Keys::Char('n')),
-    ([0xb1, 0xb1, 0x00], Keys::Char('ñ')),
+    ([0xc3, 0xb1, 0x00], Keys::Char('ñ')),
     ([0x6F, 0x00, 0x00], Keys::Char('o')),
     ([0x70, 0x00, 0x00], Keys::Char('p')),
     ([0x71, 0x00, 0x00], Keys::Char('q')),
@@ -162,7 +676,7 @@ pub const UPPER_LETTER: [([u8; BYTES], Keys); 27] = [
     ([0x4C, 0x00, 0x00], Keys::Char('L')),
     ([0x4D, 0x00, 0x00], Keys::Char('M')),
     ([0x4E, 0x00, 0x00], Keys::Char('N')),
-    ([0xb1, 0x91, 0x00], Keys::Char('Ñ')),
+    ([0xc3, 0x91, 0x00], Keys::Char('Ñ')),
     ([0x4F, 0x00, 0x00], Keys::Char('O')),
     ([0x50, 0x00, 0x00], Keys::Char('P')),
     ([0x51, 0x00, 0x00], Keys::Char('Q')),
@@ -257,7 +771,8 @@ pub const CTRL_UPPER_LETTER: [([u8; BYTES], Keys); 24] = [
 ];
 
 /// The Char('+') make a colision with Ctrl('+'),
-/// so if you need the standar feature, know that is the same hex code
+/// so if you need the standar feature, know that is the same hex code.
+/// Use `Keyboard::modify_other_keys` to tell them apart on xterm like terminals
 #[cfg(any(feature = "ctrl_standar", feature = "full",))]
 #[cfg(not(feature = "standar"))]
 pub const CTRL_STANDAR: [([u8; BYTES], Keys); 2] = [
@@ -265,7 +780,8 @@ pub const CTRL_STANDAR: [([u8; BYTES], Keys); 2] = [
     ([0x1f, 0x00, 0x00], Keys::Ctrl('-')),
 ];
 
-/// Ctrl + numbers
+/// Ctrl + numbers, same hex code as `NUMBERS` unless
+/// `Keyboard::modify_other_keys` is enabled
 #[cfg(any(
     feature = "ctrl_numbers",
     feature = "alt_numbers",
@@ -442,3 +958,75 @@ pub const ALT_GR_NUMBERS: [([u8; BYTES], Keys); 10] = [
     (CTRL_NUMBERS[8].0, Keys::AltGr('8')),
     (CTRL_NUMBERS[9].0, Keys::AltGr('9')),
 ];
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const CHARS: [char; 12] = ['a', 'z', 'A', 'Z', '0', '9', 'ñ', 'Ñ', '+', '-', '<', '^'];
+
+    /// Key codes without modifiers, with a sample of the chars
+    fn codes() -> Vec<KeyCode> {
+        let mut codes = vec![
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Enter,
+            KeyCode::Home,
+            KeyCode::Tab,
+            KeyCode::Space,
+            KeyCode::Delete,
+            KeyCode::Escape,
+            KeyCode::End,
+        ];
+        codes.extend(CHARS.map(KeyCode::Char));
+        codes.extend((1..=12).map(KeyCode::F));
+        codes
+    }
+
+    /// Every variant, with each code under every combination of modifiers
+    pub(crate) fn every_key() -> Vec<Keys> {
+        let mut keys = vec![Keys::Backtab, Keys::Null];
+        for code in codes() {
+            for modifiers in 0..8 {
+                keys.push(Keys::with_modifiers(code, Modifiers(modifiers)));
+            }
+        }
+        for c in CHARS {
+            keys.extend([Keys::Ctrl(c), Keys::Alt(c), Keys::AltGr(c)]);
+        }
+        for len in [0, 1, 2, 3, RAW_BYTES] {
+            let bytes: Vec<u8> = (0..len as u8).map(|byte| 0x1B + byte).collect();
+            keys.push(Keys::Unknown(RawBytes::new(&bytes)));
+        }
+        keys
+    }
+
+    #[test]
+    fn display_from_str_round_trip() {
+        for key in every_key() {
+            let name = key.to_string();
+            assert_eq!(
+                name.parse::<Keys>(),
+                Ok(key),
+                "{:?} written as {:?}",
+                key,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn u32_round_trip() {
+        for key in every_key() {
+            match key {
+                Keys::Unknown(raw) if raw.as_bytes().len() > 3 => {
+                    assert_eq!(key.to_u32(), u32::MAX)
+                }
+                _ => assert_eq!(Keys::from_u32(key.to_u32()), Some(key), "{:?}", key),
+            }
+        }
+        assert_eq!(Keys::from_u32(u32::MAX), None);
+    }
+}
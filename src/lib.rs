@@ -30,7 +30,7 @@
 //! # Examples
 //!
 //! ```rust,ignore
-//! use k_board::{keyboard::Keyboard, keys::Keys};
+//! use k_board::{keyboard::Keyboard, keys::Keys, output::Output};
 //!
 //!fn main() {
 //!    menu(0);
@@ -45,23 +45,67 @@
 //!}
 //!
 //!fn menu(operation: u8) {
-//!    std::process::Command::new("clear").status().unwrap();
 //!    let mut op: Vec<char> = vec!['*', ' '];
 //!    if operation == 1 {
 //!        op[0] = ' ';
 //!        op[1] = '*';
 //!    }
-//!    println!(
-//!        "[{}] I use k_board lightweight software\n[{}] I use heavyweight software",
-//!        op[0], op[1]
-//!    );
+//!    Output::new()
+//!        .clear_screen()
+//!        .text(&format!(
+//!            "[{}] I use k_board lightweight software\n[{}] I use heavyweight software\n",
+//!            op[0], op[1]
+//!        ))
+//!        .flush()
+//!        .unwrap();
 //!}
 //! ```
 //!
 
+/// Key events as an async stream for tokio & async-std
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub mod async_keyboard;
+/// Keyboard read in a background thread
+pub mod background;
+/// Tab completion for the line editor
+pub mod completion;
+/// Keybindings loaded from config files
+pub mod config;
+/// Keys back into the bytes a terminal sends
+pub mod encode;
+/// Key down & up events of Linux input devices
+#[cfg(feature = "evdev")]
+pub mod evdev;
+/// Key events with timestamps
+pub mod event;
+/// Per frame input for game loops
+pub mod game;
+/// Lines entered in the line editor
+pub mod history;
 /// Keyboard struct & impls
 pub mod keyboard;
+/// Multi-key sequences bound to actions
+pub mod keymap;
 /// All keys tables
 pub mod keys;
+/// Readline like text prompt
+pub mod line_editor;
+/// Buffered cursor, screen & color output
+pub mod output;
+/// Masked password input
+pub mod password;
+/// Fuzzy picker over long lists
+pub mod picker;
+/// Select, multi-select & confirm prompts
+pub mod prompt;
+/// Terminal queries & their responses
+pub mod query;
+/// Serde support for keys & key events
+#[cfg(feature = "serde")]
+pub mod serde_keys;
 /// Termios raw ops (linux kernel)
 pub mod termio;
+/// Vi mode of the line editor
+mod vi;
+/// Keyboard waiting along other file descriptors
+pub mod wait;
@@ -0,0 +1,795 @@
+/***************************************************************************************
+ *   line_editor.rs  --  This file is part of k_board.                                 *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    completion::{common_prefix, Completer},
+    history::History,
+    keyboard::Keyboard,
+    keys::{KeyCode, Keys, Modifiers},
+    termio::{restore_fd, setup_raw_mode_fd, terminal_size, termios},
+    vi::{Action, Vi},
+};
+use std::io::{stdout, Result, Write};
+
+/// Result of reading a line
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReadLine {
+    /// Enter was pressed
+    Line(String),
+    /// Esc (Emacs mode), Ctrl+C or Ctrl+D on an empty line was pressed
+    Cancelled,
+}
+
+/// Keys used to edit the line
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum EditMode {
+    /// Ctrl + A/E/B/F/D/W/K/U/Y & Alt + B/F/D, Esc cancels
+    #[default]
+    Emacs,
+    /// Insert mode with the Emacs keys, Esc goes to normal mode:
+    /// h l w b e 0 $ x d c D C p P i a I A u . j k & counts
+    Vi,
+}
+
+/// Text prompt over the `Keyboard`, with cursor movement, Home/End, Backspace,
+/// word jumps (Ctrl + Left/Right), kill/yank (Ctrl + K, Ctrl + U, Ctrl + Y)
+/// history (Up/Down, Ctrl + R reverse search) & completion (Tab, Shift + Tab).
+/// The printable keys are taken from `Keys::Char` or from the UTF-8 text of
+/// `Keys::Unknown`, so it works without the letter features
+pub struct LineEditor {
+    keyboard: Keyboard,
+    kill_buffer: String,
+    history: History,
+    completer: Option<Box<dyn Completer>>,
+    mode: EditMode,
+}
+
+/// State of the line being read
+#[derive(Default)]
+struct Edit {
+    line: Line,
+    /// Entry of the history shown, `None` while editing a new line
+    history_index: Option<usize>,
+    /// New line kept while browsing the history
+    draft: Line,
+    search: Option<Search>,
+    menu: Option<Menu>,
+    vi: Vi,
+}
+
+/// Completion candidates shown under the line, Tab & Shift + Tab cycle them
+struct Menu {
+    /// Char index where the completed word starts
+    start: usize,
+    /// Word typed before the menu, back on Esc
+    word: String,
+    candidates: Vec<String>,
+    selected: Option<usize>,
+}
+
+/// Ctrl + R reverse incremental search
+struct Search {
+    query: String,
+    found: Option<usize>,
+    /// Line before the search, back on cancel
+    original: Line,
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineEditor {
+    /// Get a LineEditor over a new `Keyboard`
+    pub fn new() -> Self {
+        Self::with_keyboard(Keyboard::new())
+    }
+
+    /// Get a LineEditor over `keyboard`
+    pub fn with_keyboard(keyboard: Keyboard) -> Self {
+        LineEditor {
+            keyboard,
+            kill_buffer: String::new(),
+            history: History::new(),
+            completer: None,
+            mode: EditMode::Emacs,
+        }
+    }
+
+    /// Edit with the Emacs (default) or the vi keys
+    pub fn edit_mode(mut self, mode: EditMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Complete the word under the cursor with `completer` on Tab
+    pub fn with_completer(mut self, completer: impl Completer + 'static) -> Self {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    /// Use `history`, the lines read are added to it
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// The history, to load or save it
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Show `prompt` & edit a line until Enter, Esc or Ctrl+C. The raw mode
+    /// has no signals, so Ctrl+C arrives as a key & the terminal is restored
+    pub fn read_line(&mut self, prompt: &str) -> Result<ReadLine> {
+        let fd = self.keyboard.fd();
+        let termios_enviroment: termios = setup_raw_mode_fd(fd, false)?;
+        let read = self.edit(prompt);
+        stdout().flush()?;
+        restore_fd(fd, &termios_enviroment)?;
+        if let Ok(ReadLine::Line(line)) = &read {
+            self.history.add(line);
+        }
+        read
+    }
+
+    fn edit(&mut self, prompt: &str) -> Result<ReadLine> {
+        let mut edit = Edit::default();
+        render(prompt, &edit.line, &[])?;
+        loop {
+            let key = self.keyboard.next().unwrap_or(Keys::Null);
+            let done = match (&edit.search, &edit.menu) {
+                (Some(_), _) => self.search(&mut edit, normalize(key)),
+                (None, Some(_)) => self.menu(&mut edit, normalize(key)),
+                (None, None) if self.mode == EditMode::Vi => self.vi(&mut edit, normalize(key)),
+                (None, None) => self.apply(&mut edit, normalize(key)),
+            };
+            if let Some(read) = done {
+                render(prompt, &edit.line, &[])?;
+                stdout().write_all(b"\r\n")?;
+                stdout().flush()?;
+                return Ok(read);
+            }
+            if key != Keys::Null {
+                match (&edit.search, &edit.menu) {
+                    (Some(search), _) => render(
+                        &format!("(reverse-i-search)`{}': ", search.query),
+                        &edit.line,
+                        &[],
+                    )?,
+                    (None, Some(menu)) => render(prompt, &edit.line, &menu_rows(menu))?,
+                    (None, None) => render(prompt, &edit.line, &[])?,
+                }
+            }
+        }
+    }
+
+    /// Apply the input to the line, returning the result when the line is done
+    fn apply(&mut self, edit: &mut Edit, input: Input) -> Option<ReadLine> {
+        let key = match input {
+            Input::Text(text) => {
+                edit.line.insert(&text);
+                return None;
+            }
+            Input::Key(key) => key,
+        };
+        let line = &mut edit.line;
+        match key {
+            Keys::Enter => return Some(ReadLine::Line(line.text())),
+            Keys::Escape | Keys::Ctrl('c') => return Some(ReadLine::Cancelled),
+            Keys::Left | Keys::Ctrl('b') => line.left(),
+            Keys::Right | Keys::Ctrl('f') => line.right(),
+            Keys::Modified(Modifiers::CTRL, KeyCode::Left) | Keys::Alt('b') => line.word_left(),
+            Keys::Modified(Modifiers::CTRL, KeyCode::Right) | Keys::Alt('f') => line.word_right(),
+            Keys::Home | Keys::Ctrl('a') => line.cursor = 0,
+            Keys::End | Keys::Ctrl('e') => line.cursor = line.chars.len(),
+            Keys::Delete | Keys::Ctrl('h') => line.backspace(),
+            Keys::Ctrl('d') if line.chars.is_empty() => return Some(ReadLine::Cancelled),
+            Keys::Ctrl('d') => line.delete(),
+            Keys::Ctrl('w') => self.kill_buffer = line.kill(line.space_start(), line.cursor),
+            Keys::Alt('d') => self.kill_buffer = line.kill(line.cursor, line.word_end()),
+            Keys::Ctrl('k') => self.kill_buffer = line.kill_to_end(),
+            Keys::Ctrl('u') => self.kill_buffer = line.kill_to_start(),
+            Keys::Ctrl('y') => line.insert(&self.kill_buffer.clone()),
+            Keys::Tab => self.complete(edit),
+            Keys::Up | Keys::Ctrl('p') => self.older(edit),
+            Keys::Down | Keys::Ctrl('n') => self.newer(edit),
+            Keys::Ctrl('r') => {
+                edit.search = Some(Search {
+                    query: String::new(),
+                    found: None,
+                    original: edit.line.clone(),
+                })
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Input in vi mode: insert mode works as the Emacs mode until Esc
+    fn vi(&mut self, edit: &mut Edit, input: Input) -> Option<ReadLine> {
+        if !edit.vi.normal {
+            match &input {
+                Input::Key(Keys::Escape) => {
+                    edit.vi.escape(&mut edit.line);
+                    return None;
+                }
+                Input::Text(text) => edit.vi.typed(text),
+                Input::Key(Keys::Delete | Keys::Ctrl('h')) => edit.vi.erased(),
+                _ => {}
+            }
+            return self.apply(edit, input);
+        }
+        match edit.vi.normal(&mut edit.line, &mut self.kill_buffer, input) {
+            Action::None => {}
+            Action::Submit => return Some(ReadLine::Line(edit.line.text())),
+            Action::Cancel => return Some(ReadLine::Cancelled),
+            Action::Older => self.older(edit),
+            Action::Newer => self.newer(edit),
+        }
+        if edit.vi.normal {
+            Vi::clamp(&mut edit.line);
+        }
+        None
+    }
+
+    /// Ask the completer for the word under the cursor: a single candidate
+    /// replaces it, several ones insert their common prefix or open the menu
+    fn complete(&self, edit: &mut Edit) {
+        let Some(completer) = &self.completer else {
+            return;
+        };
+        let text = edit.line.text();
+        let pos = edit.line.chars[..edit.line.cursor]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum();
+        let (start, candidates) = completer.complete(&text, pos);
+        let Some(word) = text.get(start..pos) else {
+            return;
+        };
+        let start = text[..start].chars().count();
+        match candidates.len() {
+            0 => {}
+            1 => {
+                edit.line.kill(start, edit.line.cursor);
+                edit.line.insert(&candidates[0]);
+            }
+            _ => {
+                let prefix = common_prefix(&candidates);
+                if prefix.len() > word.len() && prefix.starts_with(word) {
+                    edit.line.kill(start, edit.line.cursor);
+                    edit.line.insert(prefix);
+                    return;
+                }
+                edit.menu = Some(Menu {
+                    start,
+                    word: String::from(word),
+                    candidates,
+                    selected: None,
+                });
+            }
+        }
+    }
+
+    /// Input while the menu is shown: Tab & Shift + Tab put the next or previous
+    /// candidate, Esc puts back the typed word & any other key keeps the candidate
+    fn menu(&mut self, edit: &mut Edit, input: Input) -> Option<ReadLine> {
+        let menu = edit.menu.as_mut()?;
+        let len = menu.candidates.len();
+        let selected = match input {
+            Input::Key(Keys::Tab) => menu.selected.map_or(0, |selected| (selected + 1) % len),
+            Input::Key(Keys::Backtab) => menu
+                .selected
+                .map_or(len - 1, |selected| (selected + len - 1) % len),
+            Input::Key(Keys::Null) => return None,
+            Input::Key(Keys::Escape) => {
+                edit.line.kill(menu.start, edit.line.cursor);
+                edit.line.insert(&menu.word);
+                edit.menu = None;
+                return None;
+            }
+            input => {
+                edit.menu = None;
+                return self.apply(edit, input);
+            }
+        };
+        menu.selected = Some(selected);
+        edit.line.kill(menu.start, edit.line.cursor);
+        edit.line.insert(&menu.candidates[selected]);
+        None
+    }
+
+    /// Show the previous entry of the history
+    fn older(&self, edit: &mut Edit) {
+        let index = match edit.history_index {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                edit.draft = edit.line.clone();
+                self.history.len() - 1
+            }
+        };
+        edit.history_index = Some(index);
+        edit.line = Line::from(self.history.get(index).unwrap_or_default());
+    }
+
+    /// Show the next entry of the history, or the new line after the last one
+    fn newer(&self, edit: &mut Edit) {
+        match edit.history_index {
+            None => {}
+            Some(index) if index + 1 >= self.history.len() => {
+                edit.history_index = None;
+                edit.line = std::mem::take(&mut edit.draft);
+            }
+            Some(index) => {
+                edit.history_index = Some(index + 1);
+                edit.line = Line::from(self.history.get(index + 1).unwrap_or_default());
+            }
+        }
+    }
+
+    /// Input while searching: text & Backspace change the query, Ctrl + R looks
+    /// for an older match, Esc or Ctrl + G cancel & any other key takes the match
+    fn search(&mut self, edit: &mut Edit, input: Input) -> Option<ReadLine> {
+        let search = edit.search.as_mut()?;
+        let found = match input {
+            Input::Text(text) => {
+                search.query.push_str(&text);
+                let from = search.found.map_or(self.history.len(), |index| index + 1);
+                self.history.search(&search.query, from)
+            }
+            Input::Key(Keys::Delete | Keys::Ctrl('h')) => {
+                search.query.pop();
+                self.history.search(&search.query, self.history.len())
+            }
+            Input::Key(Keys::Ctrl('r')) => {
+                let from = search.found.unwrap_or(self.history.len());
+                self.history.search(&search.query, from).or(search.found)
+            }
+            Input::Key(Keys::Null) => return None,
+            Input::Key(Keys::Escape | Keys::Ctrl('g')) => {
+                edit.line = search.original.clone();
+                edit.search = None;
+                return None;
+            }
+            input => {
+                edit.search = None;
+                return self.apply(edit, input);
+            }
+        };
+        search.found = found;
+        if let Some(index) = found {
+            let entry = self.history.get(index).unwrap_or_default();
+            let at = entry.find(search.query.as_str()).unwrap_or(0);
+            edit.line = Line::from(entry);
+            edit.line.cursor = entry[..at].chars().count();
+            edit.history_index = Some(index);
+        }
+        None
+    }
+}
+
+/// Key read, or text to insert
+pub(crate) enum Input {
+    Key(Keys),
+    Text(String),
+}
+
+/// Turn the keys into text or into the same keys no matter the enabled features:
+/// the bytes of `Keys::Unknown` are text, Ctrl + letter, Alt + letter or Esc
+pub(crate) fn normalize(key: Keys) -> Input {
+    match key {
+        Keys::Char(c) => Input::Text(c.to_string()),
+        Keys::Space => Input::Text(String::from(" ")),
+        Keys::Ctrl(c) => Input::Key(Keys::Ctrl(c.to_ascii_lowercase())),
+        Keys::Unknown(raw) => match raw.as_bytes() {
+            [0x1B] => Input::Key(Keys::Escape),
+            [0x7F] => Input::Key(Keys::Delete),
+            [0x09] => Input::Key(Keys::Tab),
+            [0x0D] => Input::Key(Keys::Enter),
+            [0x1B, 0x5B, 0x5A] => Input::Key(Keys::Backtab),
+            &[byte @ 0x01..=0x1A] => Input::Key(Keys::Ctrl((b'a' + byte - 1) as char)),
+            [0x1B, rest @ ..] => match std::str::from_utf8(rest).map(|text| text.chars()) {
+                Ok(mut chars) => match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_control() => Input::Key(Keys::Alt(c)),
+                    _ => Input::Key(key),
+                },
+                Err(_) => Input::Key(key),
+            },
+            bytes => match std::str::from_utf8(bytes) {
+                Ok(text) if !text.chars().any(char::is_control) => Input::Text(String::from(text)),
+                _ => Input::Key(key),
+            },
+        },
+        key => Input::Key(key),
+    }
+}
+
+/// Text being edited & the cursor, as a char index
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Line {
+    pub(crate) chars: Vec<char>,
+    pub(crate) cursor: usize,
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        Line {
+            cursor: chars.len(),
+            chars,
+        }
+    }
+}
+
+impl Line {
+    pub(crate) fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub(crate) fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    pub(crate) fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub(crate) fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+    }
+
+    /// Start of the word before the cursor
+    pub(crate) fn word_start(&self) -> usize {
+        let mut cursor = self.cursor;
+        while cursor > 0 && !self.chars[cursor - 1].is_alphanumeric() {
+            cursor -= 1;
+        }
+        while cursor > 0 && self.chars[cursor - 1].is_alphanumeric() {
+            cursor -= 1;
+        }
+        cursor
+    }
+
+    /// End of the word after the cursor
+    pub(crate) fn word_end(&self) -> usize {
+        let mut cursor = self.cursor;
+        while cursor < self.chars.len() && !self.chars[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        while cursor < self.chars.len() && self.chars[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        cursor
+    }
+
+    pub(crate) fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    pub(crate) fn word_right(&mut self) {
+        self.cursor = self.word_end();
+    }
+
+    /// Start of the whitespace separated word before the cursor
+    pub(crate) fn space_start(&self) -> usize {
+        let mut cursor = self.cursor;
+        while cursor > 0 && self.chars[cursor - 1].is_whitespace() {
+            cursor -= 1;
+        }
+        while cursor > 0 && !self.chars[cursor - 1].is_whitespace() {
+            cursor -= 1;
+        }
+        cursor
+    }
+
+    /// Remove the char under the cursor
+    pub(crate) fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub(crate) fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Remove the chars in `start..end`, returning them
+    pub(crate) fn kill(&mut self, start: usize, end: usize) -> String {
+        self.cursor = start;
+        self.chars.drain(start..end).collect()
+    }
+
+    pub(crate) fn kill_to_end(&mut self) -> String {
+        self.kill(self.cursor, self.chars.len())
+    }
+
+    pub(crate) fn kill_to_start(&mut self) -> String {
+        self.kill(0, self.cursor)
+    }
+}
+
+/// Draw the prompt & the line over the current row, scrolling the line
+/// sideways when it doesn't fit in the terminal, & the `below` rows under it
+pub(crate) fn render(prompt: &str, line: &Line, below: &[String]) -> Result<()> {
+    let columns = columns();
+    let prompt_width = str_width(prompt);
+    let available = columns.saturating_sub(prompt_width + 1).max(1);
+    let mut start = 0;
+    while chars_width(&line.chars[start..line.cursor]) >= available {
+        start += 1;
+    }
+    let mut visible = String::new();
+    let mut used = 0;
+    for &c in &line.chars[start..] {
+        used += char_width(c);
+        if used > available {
+            break;
+        }
+        visible.push(c);
+    }
+    let mut out = format!("\r{}{}\x1b[J", prompt, visible);
+    for row in below {
+        out.push_str("\r\n");
+        out.push_str(row);
+    }
+    if !below.is_empty() {
+        out.push_str(&format!("\x1b[{}A", below.len()));
+    }
+    out.push('\r');
+    let cursor = prompt_width + chars_width(&line.chars[start..line.cursor]);
+    if cursor > 0 {
+        out.push_str(&format!("\x1b[{}C", cursor));
+    }
+    let mut stdout = stdout();
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()
+}
+
+/// Terminal width, 80 when it can't be known
+pub(crate) fn columns() -> usize {
+    match terminal_size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => 80,
+    }
+}
+
+/// Rows shown at most by the completion menu
+const MENU_ROWS: usize = 8;
+
+/// Candidates in columns, top to bottom, with the selected one in reverse video.
+/// Only the rows around the selected one are kept when they don't fit
+fn menu_rows(menu: &Menu) -> Vec<String> {
+    let columns = columns();
+    let width = menu
+        .candidates
+        .iter()
+        .map(|candidate| str_width(candidate))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let width = width.min(columns.saturating_sub(1)).max(1);
+    let per_row = (columns / width).max(1);
+    let rows = menu.candidates.len().div_ceil(per_row);
+    let first = match menu.selected {
+        Some(selected) => (selected % rows).saturating_sub(MENU_ROWS - 1),
+        None => 0,
+    };
+    (first..rows.min(first + MENU_ROWS))
+        .map(|row| {
+            let mut out = String::new();
+            for index in (row..menu.candidates.len()).step_by(rows) {
+                let mut cell = String::new();
+                let mut used = 0;
+                for c in menu.candidates[index].chars() {
+                    if used + char_width(c) > width - 1 {
+                        break;
+                    }
+                    used += char_width(c);
+                    cell.push(c);
+                }
+                let padding = " ".repeat(width - used);
+                match menu.selected == Some(index) {
+                    true => out.push_str(&format!("\x1b[7m{}\x1b[0m{}", cell, padding)),
+                    false => out.push_str(&format!("{}{}", cell, padding)),
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// Columns taken by `text` in the terminal
+pub(crate) fn str_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn chars_width(chars: &[char]) -> usize {
+    chars.iter().copied().map(char_width).sum()
+}
+
+/// Columns taken by `c`: 0 for control & combining chars, 2 for East Asian
+/// wide chars & emoji, 1 for the rest
+pub(crate) fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x00..=0x1F | 0x7F..=0x9F => 0,
+        0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::RawBytes;
+
+    /// Line with the cursor at `cursor`
+    fn at(text: &str, cursor: usize) -> Line {
+        Line {
+            cursor,
+            ..Line::from(text)
+        }
+    }
+
+    fn text(input: Input) -> Option<String> {
+        match input {
+            Input::Text(text) => Some(text),
+            Input::Key(_) => None,
+        }
+    }
+
+    fn key(input: Input) -> Option<Keys> {
+        match input {
+            Input::Key(key) => Some(key),
+            Input::Text(_) => None,
+        }
+    }
+
+    fn unknown(bytes: &[u8]) -> Keys {
+        Keys::Unknown(RawBytes::new(bytes))
+    }
+
+    #[test]
+    fn insert_and_erase() {
+        let mut line = at("ac", 1);
+        line.insert("bñ");
+        assert_eq!((line.text(), line.cursor), (String::from("abñc"), 3));
+        line.backspace();
+        line.delete();
+        assert_eq!((line.text(), line.cursor), (String::from("ab"), 2));
+        line.delete();
+        line.right();
+        assert_eq!((line.text(), line.cursor), (String::from("ab"), 2));
+        let mut line = Line::from("x");
+        line.cursor = 0;
+        line.backspace();
+        line.left();
+        assert_eq!((line.text(), line.cursor), (String::from("x"), 0));
+    }
+
+    #[test]
+    fn words() {
+        let mut line = at("foo, bar.baz  ", 14);
+        assert_eq!(line.word_start(), 9);
+        assert_eq!(line.space_start(), 5);
+        line.word_left();
+        line.word_left();
+        assert_eq!(line.cursor, 5);
+        assert_eq!(line.word_end(), 8);
+        line.word_right();
+        line.word_right();
+        assert_eq!(line.cursor, 12);
+        line.cursor = 0;
+        line.word_right();
+        assert_eq!(line.cursor, 3);
+    }
+
+    #[test]
+    fn kills() {
+        let mut line = at("foo bar baz", 4);
+        assert_eq!(line.kill_to_end(), "bar baz");
+        assert_eq!((line.text(), line.cursor), (String::from("foo "), 4));
+        assert_eq!(line.kill_to_start(), "foo ");
+        assert_eq!((line.text(), line.cursor), (String::new(), 0));
+        let mut line = at("foo bar baz", 8);
+        assert_eq!(line.kill(4, 8), "bar ");
+        assert_eq!((line.text(), line.cursor), (String::from("foo baz"), 4));
+    }
+
+    #[test]
+    fn normalize_keys() {
+        assert_eq!(text(normalize(Keys::Char('ñ'))), Some(String::from("ñ")));
+        assert_eq!(text(normalize(Keys::Space)), Some(String::from(" ")));
+        assert_eq!(key(normalize(Keys::Ctrl('A'))), Some(Keys::Ctrl('a')));
+        assert_eq!(key(normalize(Keys::Up)), Some(Keys::Up));
+        assert_eq!(key(normalize(unknown(b"\x1b"))), Some(Keys::Escape));
+        assert_eq!(key(normalize(unknown(b"\x7f"))), Some(Keys::Delete));
+        assert_eq!(key(normalize(unknown(b"\t"))), Some(Keys::Tab));
+        assert_eq!(key(normalize(unknown(b"\r"))), Some(Keys::Enter));
+        assert_eq!(key(normalize(unknown(b"\x1b[Z"))), Some(Keys::Backtab));
+        assert_eq!(key(normalize(unknown(b"\x01"))), Some(Keys::Ctrl('a')));
+        assert_eq!(key(normalize(unknown(b"\x1a"))), Some(Keys::Ctrl('z')));
+        assert_eq!(key(normalize(unknown(b"\x1bb"))), Some(Keys::Alt('b')));
+        assert_eq!(
+            key(normalize(unknown("\x1bñ".as_bytes()))),
+            Some(Keys::Alt('ñ'))
+        );
+        assert_eq!(text(normalize(unknown(b"a"))), Some(String::from("a")));
+        assert_eq!(
+            text(normalize(unknown("ñé".as_bytes()))),
+            Some(String::from("ñé"))
+        );
+        let csi = unknown(b"\x1b[1;2P");
+        assert_eq!(key(normalize(csi)), Some(csi));
+        assert_eq!(
+            key(normalize(unknown(b"\x1b\x01"))),
+            Some(unknown(b"\x1b\x01"))
+        );
+        assert_eq!(key(normalize(unknown(b"\xff"))), Some(unknown(b"\xff")));
+        assert_eq!(key(normalize(unknown(b"\x00"))), Some(unknown(b"\x00")));
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("日本"), 4);
+        assert_eq!(str_width("e\u{301}"), 1);
+        assert_eq!(str_width("\x1b"), 0);
+        assert_eq!(char_width('🙂'), 2);
+    }
+}
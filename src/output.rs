@@ -0,0 +1,233 @@
+/***************************************************************************************
+ *   output.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use std::io::{stdout, Result, Stdout, Write};
+
+/// SGR colors, the 8 basic ones, their bright versions, the 256 palette & RGB
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// Index in the 256 colors palette
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+    /// Color of the terminal theme
+    Default,
+}
+
+impl Color {
+    /// SGR parameters, `base` is 30 for the foreground & 40 for the background
+    fn sgr(self, base: u8) -> String {
+        match self {
+            Color::Black => format!("{}", base),
+            Color::Red => format!("{}", base + 1),
+            Color::Green => format!("{}", base + 2),
+            Color::Yellow => format!("{}", base + 3),
+            Color::Blue => format!("{}", base + 4),
+            Color::Magenta => format!("{}", base + 5),
+            Color::Cyan => format!("{}", base + 6),
+            Color::White => format!("{}", base + 7),
+            Color::BrightBlack => format!("{}", base + 60),
+            Color::BrightRed => format!("{}", base + 61),
+            Color::BrightGreen => format!("{}", base + 62),
+            Color::BrightYellow => format!("{}", base + 63),
+            Color::BrightBlue => format!("{}", base + 64),
+            Color::BrightMagenta => format!("{}", base + 65),
+            Color::BrightCyan => format!("{}", base + 66),
+            Color::BrightWhite => format!("{}", base + 67),
+            Color::Ansi(index) => format!("{};5;{}", base + 8, index),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+            Color::Default => format!("{}", base + 9),
+        }
+    }
+}
+
+/// Escape sequences & text kept in memory until `flush()`, so a whole frame
+/// reaches the terminal in one write, without flicker or a `clear` process
+pub struct Output<W: Write = Stdout> {
+    out: W,
+    buffer: Vec<u8>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Output {
+    /// Get an Output over stdout
+    pub fn new() -> Self {
+        Self::with_writer(stdout())
+    }
+}
+
+impl<W: Write> Output<W> {
+    /// Get an Output over `out`, like a `/dev/tty` file
+    pub fn with_writer(out: W) -> Self {
+        Output {
+            out,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Add `text` as is
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.buffer.extend_from_slice(text.as_bytes());
+        self
+    }
+
+    /// Move the cursor to `row` & `column`, starting at 1
+    pub fn move_to(&mut self, row: u16, column: u16) -> &mut Self {
+        self.text(&format!("\x1b[{};{}H", row.max(1), column.max(1)))
+    }
+
+    pub fn move_up(&mut self, rows: u16) -> &mut Self {
+        self.relative(rows, 'A')
+    }
+
+    pub fn move_down(&mut self, rows: u16) -> &mut Self {
+        self.relative(rows, 'B')
+    }
+
+    pub fn move_right(&mut self, columns: u16) -> &mut Self {
+        self.relative(columns, 'C')
+    }
+
+    pub fn move_left(&mut self, columns: u16) -> &mut Self {
+        self.relative(columns, 'D')
+    }
+
+    /// Terminals take 0 as 1, so nothing is written for 0
+    fn relative(&mut self, count: u16, direction: char) -> &mut Self {
+        match count {
+            0 => self,
+            count => self.text(&format!("\x1b[{}{}", count, direction)),
+        }
+    }
+
+    /// Clear the whole screen & move the cursor to the top left corner
+    pub fn clear_screen(&mut self) -> &mut Self {
+        self.text("\x1b[2J\x1b[H")
+    }
+
+    /// Clear from the cursor to the end of the screen
+    pub fn clear_below(&mut self) -> &mut Self {
+        self.text("\x1b[J")
+    }
+
+    /// Clear the row of the cursor & move it to the first column
+    pub fn clear_line(&mut self) -> &mut Self {
+        self.text("\x1b[2K\r")
+    }
+
+    /// Clear from the cursor to the end of the row
+    pub fn clear_to_end_of_line(&mut self) -> &mut Self {
+        self.text("\x1b[K")
+    }
+
+    pub fn hide_cursor(&mut self) -> &mut Self {
+        self.text("\x1b[?25l")
+    }
+
+    pub fn show_cursor(&mut self) -> &mut Self {
+        self.text("\x1b[?25h")
+    }
+
+    /// Keep the cursor position for `restore_cursor` (`ESC 7`)
+    pub fn save_cursor(&mut self) -> &mut Self {
+        self.text("\x1b7")
+    }
+
+    /// Back to the position kept by `save_cursor` (`ESC 8`)
+    pub fn restore_cursor(&mut self) -> &mut Self {
+        self.text("\x1b8")
+    }
+
+    /// Switch to the alternate screen (`CSI ? 1049 h`), the shell contents
+    /// are back when leaving it
+    pub fn enter_alternate_screen(&mut self) -> &mut Self {
+        self.text("\x1b[?1049h")
+    }
+
+    pub fn leave_alternate_screen(&mut self) -> &mut Self {
+        self.text("\x1b[?1049l")
+    }
+
+    /// Color of the text written next
+    pub fn fg(&mut self, color: Color) -> &mut Self {
+        self.text(&format!("\x1b[{}m", color.sgr(30)))
+    }
+
+    /// Background color of the text written next
+    pub fn bg(&mut self, color: Color) -> &mut Self {
+        self.text(&format!("\x1b[{}m", color.sgr(40)))
+    }
+
+    pub fn bold(&mut self) -> &mut Self {
+        self.text("\x1b[1m")
+    }
+
+    pub fn underline(&mut self) -> &mut Self {
+        self.text("\x1b[4m")
+    }
+
+    /// Swap the text & background colors
+    pub fn reverse(&mut self) -> &mut Self {
+        self.text("\x1b[7m")
+    }
+
+    /// Back to the default colors & style
+    pub fn reset(&mut self) -> &mut Self {
+        self.text("\x1b[0m")
+    }
+
+    /// Write the frame in one go & empty the buffer
+    pub fn flush(&mut self) -> Result<()> {
+        self.out.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Output::flush(self)
+    }
+}
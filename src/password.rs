@@ -0,0 +1,197 @@
+/***************************************************************************************
+ *   password.rs  --  This file is part of k_board.                                    *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keyboard::decode,
+    keys::Keys,
+    line_editor::char_width,
+    termio::{restore_fd, setup_raw_mode_fd, termios},
+};
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Result, Write},
+    os::unix::io::AsRawFd,
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+/// Bytes reserved up front, so most passwords are never moved in memory
+const CAPACITY: usize = 128;
+
+/// Text read by `read_password`, overwritten with zeros when dropped.
+/// `Debug` doesn't show it, so it can't leak into logs or panic messages
+pub struct Password {
+    text: String,
+}
+
+impl Password {
+    fn new() -> Self {
+        Password {
+            text: String::with_capacity(CAPACITY),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Add `c`, moving the text to a bigger buffer by hand when it's full so
+    /// the old one is zeroed too
+    fn push(&mut self, c: char) {
+        if self.text.len() + c.len_utf8() > self.text.capacity() {
+            let mut text = String::with_capacity(self.text.capacity() * 2 + c.len_utf8());
+            text.push_str(&self.text);
+            zeroize(&mut self.text);
+            self.text = text;
+        }
+        self.text.push(c);
+    }
+
+    fn pop(&mut self) {
+        self.text.pop();
+        zeroize_spare(&mut self.text);
+    }
+
+    fn clear(&mut self) {
+        self.text.clear();
+        zeroize_spare(&mut self.text);
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        zeroize(&mut self.text);
+    }
+}
+
+/// Overwrite all the buffer of `text` with zeros & empty it
+fn zeroize(text: &mut String) {
+    text.clear();
+    zeroize_spare(text);
+}
+
+/// Overwrite the unused part of the buffer of `text` with zeros
+fn zeroize_spare(text: &mut String) {
+    let bytes = unsafe { text.as_mut_vec() };
+    zeroize_bytes(
+        bytes.spare_capacity_mut().as_mut_ptr() as *mut u8,
+        bytes.capacity() - bytes.len(),
+    );
+}
+
+/// Volatile writes, so the compiler can't skip them as dead stores
+fn zeroize_bytes(bytes: *mut u8, len: usize) {
+    for index in 0..len {
+        unsafe { ptr::write_volatile(bytes.add(index), 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Show `prompt` & read a password from `/dev/tty`, even when stdin is
+/// redirected, with echo off. Each char typed shows `mask` if any, Backspace
+/// removes the last one & Ctrl + U all of them. Enter returns the password,
+/// Esc & Ctrl + C give an `ErrorKind::Interrupted` error
+pub fn read_password(prompt: &str, mask: Option<char>) -> Result<Password> {
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let termios_enviroment: termios = setup_raw_mode_fd(tty.as_raw_fd(), false)?;
+    let password = read(&mut tty, prompt, mask);
+    restore_fd(tty.as_raw_fd(), &termios_enviroment)?;
+    tty.write_all(b"\r\n")?;
+    password
+}
+
+fn read(tty: &mut File, prompt: &str, mask: Option<char>) -> Result<Password> {
+    let mut password = Password::new();
+    let mut shown = 0;
+    write!(tty, "{}", prompt)?;
+    loop {
+        let mut buffer = [0u8; 32];
+        let read = tty.read(&mut buffer);
+        let mut done = None;
+        let mut bytes = &buffer[..*read.as_ref().unwrap_or(&0)];
+        while !bytes.is_empty() && done.is_none() {
+            let (key, used) = decode(bytes, false);
+            bytes = &bytes[used.max(1)..];
+            done = apply(&mut password, key);
+        }
+        zeroize_bytes(buffer.as_mut_ptr(), buffer.len());
+        read?;
+        if let Some(mask) = mask {
+            let count = password.text.chars().count();
+            if count != shown {
+                let text = mask.to_string().repeat(count);
+                write!(tty, "\r{}{}\x1b[K", prompt, text)?;
+                shown = count;
+            }
+        }
+        match done {
+            Some(true) => return Ok(password),
+            Some(false) => {
+                return Err(Error::new(
+                    ErrorKind::Interrupted,
+                    "password input cancelled",
+                ))
+            }
+            None => {}
+        }
+    }
+}
+
+/// Apply `key` to the password: `Some(true)` on Enter & `Some(false)` on cancel
+fn apply(password: &mut Password, key: Keys) -> Option<bool> {
+    match key {
+        Keys::Char(c) if char_width(c) > 0 => password.push(c),
+        Keys::Space => password.push(' '),
+        Keys::Enter => return Some(true),
+        Keys::Delete | Keys::Ctrl('h' | 'H') => password.pop(),
+        Keys::Ctrl('u' | 'U') => password.clear(),
+        Keys::Escape | Keys::Ctrl('c' | 'C') => return Some(false),
+        Keys::Unknown(raw) => match raw.as_bytes() {
+            [0x0A] | [0x0D] => return Some(true),
+            [0x7F] | [0x08] => password.pop(),
+            [0x15] => password.clear(),
+            [0x1B] | [0x03] => return Some(false),
+            bytes => match std::str::from_utf8(bytes) {
+                Ok(text) if !text.chars().any(char::is_control) => {
+                    text.chars().for_each(|c| password.push(c))
+                }
+                _ => {}
+            },
+        },
+        _ => {}
+    }
+    None
+}
@@ -0,0 +1,464 @@
+/***************************************************************************************
+ *   picker.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keyboard::Keyboard,
+    keys::Keys,
+    line_editor::{char_width, normalize, str_width, Input},
+    termio::{restore_fd, setup_raw_mode_fd, terminal_size_fd, termios},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{stdin, BufRead, Result, Write},
+};
+
+/// Rows of results shown by default
+const HEIGHT: usize = 10;
+
+/// Result of `fuzzy_match`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Match {
+    /// Higher is better
+    pub score: i64,
+    /// Char indexes of the item matched by the query
+    pub positions: Vec<usize>,
+}
+
+/// Match the chars of `query` in order inside `item`, with a score that favors
+/// consecutive chars, word starts & short gaps. It ignores case unless the
+/// query has uppercase letters
+pub fn fuzzy_match(query: &str, item: &str) -> Option<Match> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| match case_sensitive {
+        true => c,
+        false => c.to_lowercase().next().unwrap_or(c),
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let chars: Vec<char> = item.chars().collect();
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    // first match from the left, then the tightest one ending there
+    let mut next = 0;
+    let mut end = None;
+    for (index, &c) in chars.iter().enumerate() {
+        if fold(c) == query[next] {
+            next += 1;
+            if next == query.len() {
+                end = Some(index);
+                break;
+            }
+        }
+    }
+    let mut index = end?;
+    let mut positions = vec![0; query.len()];
+    for (slot, &wanted) in positions.iter_mut().zip(&query).rev() {
+        while fold(chars[index]) != wanted {
+            index -= 1;
+        }
+        *slot = index;
+        index = index.saturating_sub(1);
+    }
+    let mut score = 0;
+    for (n, &position) in positions.iter().enumerate() {
+        score += 16;
+        let boundary = match position.checked_sub(1).map(|previous| chars[previous]) {
+            None => true,
+            Some(previous) => {
+                !previous.is_alphanumeric()
+                    || (previous.is_lowercase() && chars[position].is_uppercase())
+            }
+        };
+        if boundary {
+            score += if n == 0 { 20 } else { 10 };
+        }
+        if n > 0 {
+            match position - positions[n - 1] - 1 {
+                0 => score += 8,
+                gap => score -= 3 + gap as i64,
+            }
+        }
+    }
+    Some(Match { score, positions })
+}
+
+/// fzf like picker: typing filters the items with `fuzzy_match`, best first.
+/// Up/Down move, Tab & Shift + Tab mark items in multi mode, Enter accepts &
+/// Esc or Ctrl + C give `None`. Keys are read & rows drawn on `/dev/tty`,
+/// so stdin & stdout are free for the items & the selection
+pub struct Picker {
+    items: Vec<String>,
+    prompt: String,
+    multi: bool,
+    height: usize,
+}
+
+impl Picker {
+    pub fn new<I: IntoIterator<Item = T>, T: Into<String>>(items: I) -> Self {
+        Picker {
+            items: items.into_iter().map(Into::into).collect(),
+            prompt: String::from("> "),
+            multi: false,
+            height: HEIGHT,
+        }
+    }
+
+    /// Picker over the lines of stdin
+    pub fn from_stdin() -> Result<Self> {
+        let items = stdin().lock().lines().collect::<Result<Vec<String>>>()?;
+        Ok(Self::new(items))
+    }
+
+    /// Text before the query, `"> "` by default
+    pub fn prompt(mut self, prompt: &str) -> Self {
+        self.prompt = String::from(prompt);
+        self
+    }
+
+    /// Let Tab & Shift + Tab mark several items
+    pub fn multi(mut self, multi: bool) -> Self {
+        self.multi = multi;
+        self
+    }
+
+    /// Rows of results shown, 10 by default
+    pub fn height(mut self, height: usize) -> Self {
+        self.height = height.max(1);
+        self
+    }
+
+    /// Items picked: the marked ones, or the one under the cursor
+    pub fn read(self) -> Result<Option<Vec<String>>> {
+        let mut tty = OpenOptions::new().write(true).open("/dev/tty")?;
+        let mut keyboard = Keyboard::from_tty()?;
+        let termios_enviroment: termios = setup_raw_mode_fd(keyboard.fd(), false)?;
+        let (columns, rows) = match terminal_size_fd(keyboard.fd()) {
+            Ok((columns, rows)) if columns > 0 && rows > 1 => (columns as usize, rows as usize),
+            _ => (80, 24),
+        };
+        let mut state = State {
+            results: (0..self.items.len())
+                .map(|index| (index, Vec::new()))
+                .collect(),
+            marked: vec![false; self.items.len()],
+            height: self.height.min(rows - 1),
+            columns,
+            query: String::new(),
+            searched: String::new(),
+            cursor: 0,
+            offset: 0,
+            picker: self,
+        };
+        let picked = state.run(&mut keyboard, &mut tty);
+        tty.write_all(b"\r\x1b[J")?;
+        restore_fd(keyboard.fd(), &termios_enviroment)?;
+        picked
+    }
+}
+
+struct State {
+    picker: Picker,
+    /// Items matching the query, best first, with the chars matched
+    results: Vec<(usize, Vec<usize>)>,
+    marked: Vec<bool>,
+    height: usize,
+    columns: usize,
+    query: String,
+    /// Query of `results`
+    searched: String,
+    /// Index in `results`
+    cursor: usize,
+    offset: usize,
+}
+
+impl State {
+    fn run(&mut self, keyboard: &mut Keyboard, tty: &mut File) -> Result<Option<Vec<String>>> {
+        self.draw(tty)?;
+        loop {
+            let key = keyboard.next().unwrap_or(Keys::Null);
+            let last = self.results.len().saturating_sub(1);
+            match normalize(key) {
+                Input::Key(Keys::Null) => continue,
+                Input::Key(Keys::Enter) => return Ok(Some(self.picked())),
+                Input::Key(Keys::Escape | Keys::Ctrl('c')) => return Ok(None),
+                Input::Key(Keys::Up | Keys::Ctrl('p')) => {
+                    self.cursor = self.cursor.saturating_sub(1)
+                }
+                Input::Key(Keys::Down | Keys::Ctrl('n')) => {
+                    self.cursor = (self.cursor + 1).min(last)
+                }
+                Input::Key(Keys::Tab) if self.picker.multi => {
+                    self.toggle();
+                    self.cursor = (self.cursor + 1).min(last);
+                }
+                Input::Key(Keys::Backtab) if self.picker.multi => {
+                    self.toggle();
+                    self.cursor = self.cursor.saturating_sub(1);
+                }
+                Input::Key(Keys::Delete | Keys::Ctrl('h')) => {
+                    self.query.pop();
+                }
+                Input::Key(Keys::Ctrl('u')) => self.query.clear(),
+                Input::Text(text) => self.query.push_str(&text),
+                Input::Key(_) => {}
+            }
+            if self.query != self.searched {
+                self.search();
+            }
+            if self.cursor < self.offset {
+                self.offset = self.cursor;
+            } else if self.cursor >= self.offset + self.height {
+                self.offset = self.cursor + 1 - self.height;
+            }
+            self.draw(tty)?;
+        }
+    }
+
+    /// Rank the items again. When the query only got longer, just the last
+    /// results can match, so only they are looked at
+    fn search(&mut self) {
+        let items = &self.picker.items;
+        let candidates: Vec<usize> = match self.query.starts_with(self.searched.as_str()) {
+            true => self.results.iter().map(|&(index, _)| index).collect(),
+            false => (0..items.len()).collect(),
+        };
+        let mut scored: Vec<(i64, usize, Vec<usize>)> = candidates
+            .into_iter()
+            .filter_map(|index| {
+                let found = fuzzy_match(&self.query, &items[index])?;
+                Some((found.score, index, found.positions))
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(items[a.1].len().cmp(&items[b.1].len()))
+                .then(a.1.cmp(&b.1))
+        });
+        if self.query.is_empty() {
+            scored.sort_by_key(|&(_, index, _)| index);
+        }
+        self.results = scored
+            .into_iter()
+            .map(|(_, index, positions)| (index, positions))
+            .collect();
+        self.searched = self.query.clone();
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    fn toggle(&mut self) {
+        if let Some(&(index, _)) = self.results.get(self.cursor) {
+            self.marked[index] = !self.marked[index];
+        }
+    }
+
+    fn picked(&self) -> Vec<String> {
+        let marked: Vec<String> = (0..self.marked.len())
+            .filter(|&index| self.marked[index])
+            .map(|index| self.picker.items[index].clone())
+            .collect();
+        match (marked.is_empty(), self.results.get(self.cursor)) {
+            (false, _) => marked,
+            (true, Some(&(index, _))) => vec![self.picker.items[index].clone()],
+            (true, None) => Vec::new(),
+        }
+    }
+
+    /// Prompt row with the counters, the results under it & the cursor back
+    /// after the query
+    fn draw(&self, tty: &mut File) -> Result<()> {
+        let mut counter = format!("  {}/{}", self.results.len(), self.picker.items.len());
+        let marked = self.marked.iter().filter(|&&marked| marked).count();
+        if marked > 0 {
+            counter.push_str(&format!(" ({})", marked));
+        }
+        let prompt = format!("{}{}", self.picker.prompt, self.query);
+        let mut out = format!("\r\x1b[J{}", prompt);
+        if str_width(&prompt) + str_width(&counter) < self.columns {
+            out.push_str(&counter);
+        }
+        let shown = self
+            .results
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.height);
+        for (row, (index, positions)) in shown {
+            let pointer = if row == self.cursor { "> " } else { "  " };
+            let mark = if self.marked[*index] { "* " } else { "  " };
+            out.push_str("\r\n");
+            out.push_str(pointer);
+            out.push_str(mark);
+            let mut used = 4;
+            for (position, c) in self.picker.items[*index].chars().enumerate() {
+                let c = if c.is_control() { ' ' } else { c };
+                used += char_width(c);
+                if used >= self.columns {
+                    break;
+                }
+                match positions.contains(&position) {
+                    true => out.push_str(&format!("\x1b[1m{}\x1b[22m", c)),
+                    false => out.push(c),
+                }
+            }
+        }
+        let rows = self
+            .results
+            .len()
+            .saturating_sub(self.offset)
+            .min(self.height);
+        if rows > 0 {
+            out.push_str(&format!("\x1b[{}A", rows));
+        }
+        out.push('\r');
+        let column = str_width(&prompt).min(self.columns - 1);
+        if column > 0 {
+            out.push_str(&format!("\x1b[{}C", column));
+        }
+        tty.write_all(out.as_bytes())?;
+        tty.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, item: &str) -> Option<Vec<usize>> {
+        fuzzy_match(query, item).map(|found| found.positions)
+    }
+
+    fn score(query: &str, item: &str) -> i64 {
+        fuzzy_match(query, item).unwrap().score
+    }
+
+    fn state(items: &[&str]) -> State {
+        State {
+            results: (0..items.len()).map(|index| (index, Vec::new())).collect(),
+            marked: vec![false; items.len()],
+            height: HEIGHT,
+            columns: 80,
+            query: String::new(),
+            searched: String::new(),
+            cursor: 0,
+            offset: 0,
+            picker: Picker::new(items.iter().copied()),
+        }
+    }
+
+    fn ranked(state: &mut State, query: &str) -> Vec<String> {
+        state.query = String::from(query);
+        state.search();
+        state
+            .results
+            .iter()
+            .map(|&(index, _)| state.picker.items[index].clone())
+            .collect()
+    }
+
+    #[test]
+    fn matched_chars() {
+        assert_eq!(positions("", "anything"), Some(Vec::new()));
+        assert_eq!(positions("abc", "abc"), Some(vec![0, 1, 2]));
+        assert_eq!(positions("ac", "a_b_c"), Some(vec![0, 4]));
+        assert_eq!(positions("ca", "abc"), None);
+        assert_eq!(positions("abcd", "abc"), None);
+        // the tightest run ending at the first full match
+        assert_eq!(positions("ab", "a_xab"), Some(vec![3, 4]));
+        // char indexes, not bytes
+        assert_eq!(positions("ñu", "año_ñu"), Some(vec![4, 5]));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert_eq!(positions("rd", "README"), Some(vec![0, 3]));
+        assert_eq!(positions("Rd", "README"), None);
+        assert_eq!(positions("RD", "README"), Some(vec![0, 3]));
+        assert_eq!(positions("É", "élan"), None);
+        assert_eq!(positions("é", "Élan"), Some(vec![0]));
+    }
+
+    #[test]
+    fn scores() {
+        assert!(score("main", "main.rs") > score("main", "m_a_i_n.rs"));
+        assert!(score("kb", "keys/board") > score("kb", "kebab"));
+        assert!(score("kb", "keyBoard") > score("kb", "keyboard"));
+        assert!(score("rs", "src/rs") > score("rs", "src/ors"));
+        assert!(score("ab", "a_b") > score("ab", "a___b"));
+    }
+
+    #[test]
+    fn ranking() {
+        let mut state = state(&[
+            "src/keys.rs",
+            "Cargo.toml",
+            "keys",
+            "src/k_board.rs",
+            "README.md",
+        ]);
+        assert_eq!(
+            ranked(&mut state, ""),
+            [
+                "src/keys.rs",
+                "Cargo.toml",
+                "keys",
+                "src/k_board.rs",
+                "README.md"
+            ]
+        );
+        // same score: the shorter item first
+        assert_eq!(ranked(&mut state, "keys"), ["keys", "src/keys.rs"]);
+        assert_eq!(
+            ranked(&mut state, "ks"),
+            ["keys", "src/keys.rs", "src/k_board.rs"]
+        );
+        // the word start of `.rs` beats the `r` inside `board`
+        assert_eq!(ranked(&mut state, "kr"), ["src/keys.rs", "src/k_board.rs"]);
+        assert_eq!(ranked(&mut state, "krs"), ["src/keys.rs", "src/k_board.rs"]);
+        // a shorter query looks at every item again
+        assert_eq!(
+            ranked(&mut state, "r"),
+            ["README.md", "Cargo.toml", "src/keys.rs", "src/k_board.rs"]
+        );
+        assert_eq!(ranked(&mut state, "xyz"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn picked_items() {
+        let mut state = state(&["one", "two", "three"]);
+        ranked(&mut state, "t");
+        assert_eq!(state.picked(), ["two"]);
+        state.cursor = 1;
+        state.toggle();
+        state.cursor = 0;
+        state.toggle();
+        assert_eq!(state.picked(), ["two", "three"]);
+        state.toggle();
+        assert_eq!(state.picked(), ["three"]);
+        ranked(&mut state, "z");
+        state.toggle();
+        assert_eq!(state.picked(), ["three"]);
+        state.marked = vec![false; 3];
+        assert_eq!(state.picked(), Vec::<String>::new());
+    }
+}
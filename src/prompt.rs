@@ -0,0 +1,481 @@
+/***************************************************************************************
+ *   prompt.rs  --  This file is part of k_board.                                      *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keyboard::Keyboard,
+    keys::Keys,
+    line_editor::{char_width, columns, normalize, Input},
+    termio::{restore, setup_raw_mode_fd, terminal_size, termios},
+};
+use std::{
+    fmt::Display,
+    io::{stdout, Result, Write},
+};
+
+/// Items shown at once by default, the rest scroll
+const PAGE_SIZE: usize = 10;
+
+/// Pick one item with Up/Down & Enter, Esc or Ctrl + C give `None`
+pub struct Select {
+    prompt: String,
+    items: Vec<String>,
+    selected: usize,
+    page_size: usize,
+    filter: bool,
+}
+
+impl Select {
+    pub fn new<I: IntoIterator<Item = T>, T: Display>(prompt: &str, items: I) -> Self {
+        Select {
+            prompt: String::from(prompt),
+            items: items.into_iter().map(|item| item.to_string()).collect(),
+            selected: 0,
+            page_size: PAGE_SIZE,
+            filter: false,
+        }
+    }
+
+    /// Item under the cursor at the start
+    pub fn selected(mut self, selected: usize) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// Items shown at once, 10 by default
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Typing filters the items
+    pub fn filter(mut self, filter: bool) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Index of the item picked
+    pub fn read(self) -> Result<Option<usize>> {
+        let mut list = List::new(self.items, self.page_size, self.filter);
+        list.cursor = self.selected.min(list.shown.len().saturating_sub(1));
+        list.scroll();
+        run(&mut SelectWidget {
+            prompt: self.prompt,
+            list,
+        })
+    }
+}
+
+/// Pick several items: Space toggles the one under the cursor & Enter
+/// accepts, Esc or Ctrl + C give `None`
+pub struct MultiSelect {
+    prompt: String,
+    items: Vec<String>,
+    checked: Vec<bool>,
+    page_size: usize,
+    filter: bool,
+}
+
+impl MultiSelect {
+    pub fn new<I: IntoIterator<Item = T>, T: Display>(prompt: &str, items: I) -> Self {
+        let items: Vec<String> = items.into_iter().map(|item| item.to_string()).collect();
+        MultiSelect {
+            prompt: String::from(prompt),
+            checked: vec![false; items.len()],
+            items,
+            page_size: PAGE_SIZE,
+            filter: false,
+        }
+    }
+
+    /// Items checked at the start
+    pub fn checked(mut self, checked: &[usize]) -> Self {
+        for &index in checked {
+            if let Some(check) = self.checked.get_mut(index) {
+                *check = true;
+            }
+        }
+        self
+    }
+
+    /// Items shown at once, 10 by default
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Typing filters the items
+    pub fn filter(mut self, filter: bool) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Indexes of the items checked, in order
+    pub fn read(self) -> Result<Option<Vec<usize>>> {
+        run(&mut MultiSelectWidget {
+            prompt: self.prompt,
+            list: List::new(self.items, self.page_size, self.filter),
+            checked: self.checked,
+        })
+    }
+}
+
+/// Yes/no question: y or n answer, Enter takes the default if any, Esc or
+/// Ctrl + C give `None`
+pub struct Confirm {
+    prompt: String,
+    default: Option<bool>,
+}
+
+impl Confirm {
+    pub fn new(prompt: &str) -> Self {
+        Confirm {
+            prompt: String::from(prompt),
+            default: None,
+        }
+    }
+
+    /// Answer of Enter
+    pub fn default(mut self, default: bool) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn read(self) -> Result<Option<bool>> {
+        run(&mut ConfirmWidget {
+            prompt: self.prompt,
+            default: self.default,
+        })
+    }
+}
+
+/// Prompt drawn in place over the rows of the terminal
+trait Widget {
+    type Output;
+    /// Rows of the current frame
+    fn rows(&self, columns: usize) -> Vec<String>;
+    /// `Some` when the prompt is done, with `None` when it was cancelled
+    fn input(&mut self, input: Input) -> Option<Option<Self::Output>>;
+    /// Row left on screen once done
+    fn summary(&self, output: &Option<Self::Output>) -> String;
+}
+
+/// Raw mode without signals, so Ctrl + C arrives as a key & the terminal
+/// is always restored, with the cursor hidden while the widget is shown
+fn run<W: Widget>(widget: &mut W) -> Result<Option<W::Output>> {
+    let termios_enviroment: termios = setup_raw_mode_fd(0, false)?;
+    let output = draw(widget);
+    let mut stdout = stdout();
+    stdout.write_all(b"\x1b[?25h")?;
+    restore(&termios_enviroment)?;
+    output
+}
+
+fn draw<W: Widget>(widget: &mut W) -> Result<Option<W::Output>> {
+    let mut keyboard = Keyboard::new();
+    let mut frame = Frame { rows: 0 };
+    frame.draw(&widget.rows(columns()))?;
+    loop {
+        let key = keyboard.next().unwrap_or(Keys::Null);
+        if key == Keys::Null {
+            continue;
+        }
+        if let Some(output) = widget.input(normalize(key)) {
+            frame.draw(&[widget.summary(&output)])?;
+            stdout().write_all(b"\r\n")?;
+            return Ok(output);
+        }
+        frame.draw(&widget.rows(columns()))?;
+    }
+}
+
+/// Rows drawn last, cleared before the next frame
+struct Frame {
+    rows: usize,
+}
+
+impl Frame {
+    fn draw(&mut self, rows: &[String]) -> Result<()> {
+        let mut out = String::from("\x1b[?25l\r");
+        if self.rows > 1 {
+            out.push_str(&format!("\x1b[{}A", self.rows - 1));
+        }
+        out.push_str("\x1b[J");
+        out.push_str(&rows.join("\r\n"));
+        self.rows = rows.len();
+        let mut stdout = stdout();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Items filtered by the query & scrolled so the cursor is shown
+struct List {
+    items: Vec<String>,
+    filter: bool,
+    query: String,
+    /// Indexes of the items matching the query
+    shown: Vec<usize>,
+    /// Index in `shown`
+    cursor: usize,
+    /// First row of `shown` on screen
+    offset: usize,
+    page_size: usize,
+}
+
+impl List {
+    fn new(items: Vec<String>, page_size: usize, filter: bool) -> Self {
+        let rows = match terminal_size() {
+            Ok((_, rows)) if rows > 1 => rows as usize - 1,
+            _ => PAGE_SIZE,
+        };
+        List {
+            shown: (0..items.len()).collect(),
+            items,
+            filter,
+            query: String::new(),
+            cursor: 0,
+            offset: 0,
+            page_size: page_size.min(rows),
+        }
+    }
+
+    /// Item under the cursor
+    fn current(&self) -> Option<usize> {
+        self.shown.get(self.cursor).copied()
+    }
+
+    /// Arrows, Home/End & the query, `true` when the input was taken
+    fn input(&mut self, input: &Input) -> bool {
+        let last = self.shown.len().saturating_sub(1);
+        match input {
+            Input::Key(Keys::Up | Keys::Ctrl('p')) => {
+                self.cursor = self.cursor.checked_sub(1).unwrap_or(last)
+            }
+            Input::Key(Keys::Down | Keys::Ctrl('n')) => {
+                self.cursor = if self.cursor >= last {
+                    0
+                } else {
+                    self.cursor + 1
+                }
+            }
+            Input::Key(Keys::Home) => self.cursor = 0,
+            Input::Key(Keys::End) => self.cursor = last,
+            Input::Key(Keys::Delete | Keys::Ctrl('h')) if self.filter => {
+                self.query.pop();
+                self.refilter();
+            }
+            Input::Key(Keys::Ctrl('u')) if self.filter => {
+                self.query.clear();
+                self.refilter();
+            }
+            Input::Text(text) if self.filter => {
+                self.query.push_str(text);
+                self.refilter();
+            }
+            _ => return false,
+        }
+        self.scroll();
+        true
+    }
+
+    /// Move the rows shown so the cursor is in them
+    fn scroll(&mut self) {
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + self.page_size {
+            self.offset = self.cursor + 1 - self.page_size;
+        }
+    }
+
+    /// Keep the items containing the query, ignoring case
+    fn refilter(&mut self) {
+        let query = self.query.to_lowercase();
+        self.shown = (0..self.items.len())
+            .filter(|&index| self.items[index].to_lowercase().contains(&query))
+            .collect();
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    /// Prompt row & the visible items, each after its `mark`
+    fn rows(
+        &self,
+        prompt: &str,
+        columns: usize,
+        mark: impl Fn(usize) -> &'static str,
+    ) -> Vec<String> {
+        let mut header = String::from(prompt);
+        if self.filter {
+            header.push(' ');
+            header.push_str(&self.query);
+        }
+        let mut rows = vec![fit(&header, columns)];
+        for (row, &index) in self
+            .shown
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.page_size)
+        {
+            let pointer = match row == self.cursor {
+                true => "> ",
+                false => "  ",
+            };
+            rows.push(fit(
+                &format!("{}{}{}", pointer, mark(index), self.items[index]),
+                columns,
+            ));
+        }
+        if self.shown.is_empty() {
+            rows.push(String::from("  (no matches)"));
+        }
+        rows
+    }
+}
+
+/// `text` cut to the chars that fit in `columns` - 1, so rows never wrap
+fn fit(text: &str, columns: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|&c| {
+            used += char_width(c);
+            used < columns
+        })
+        .collect()
+}
+
+struct SelectWidget {
+    prompt: String,
+    list: List,
+}
+
+impl Widget for SelectWidget {
+    type Output = usize;
+
+    fn rows(&self, columns: usize) -> Vec<String> {
+        self.list.rows(&self.prompt, columns, |_| "")
+    }
+
+    fn input(&mut self, input: Input) -> Option<Option<usize>> {
+        match input {
+            Input::Key(Keys::Enter) => self.list.current().map(Some),
+            Input::Key(Keys::Escape | Keys::Ctrl('c')) => Some(None),
+            input => {
+                self.list.input(&input);
+                None
+            }
+        }
+    }
+
+    fn summary(&self, output: &Option<usize>) -> String {
+        match output {
+            Some(index) => format!("{} {}", self.prompt, self.list.items[*index]),
+            None => self.prompt.clone(),
+        }
+    }
+}
+
+struct MultiSelectWidget {
+    prompt: String,
+    list: List,
+    checked: Vec<bool>,
+}
+
+impl Widget for MultiSelectWidget {
+    type Output = Vec<usize>;
+
+    fn rows(&self, columns: usize) -> Vec<String> {
+        let checked = &self.checked;
+        self.list
+            .rows(&self.prompt, columns, |index| match checked[index] {
+                true => "[x] ",
+                false => "[ ] ",
+            })
+    }
+
+    fn input(&mut self, input: Input) -> Option<Option<Vec<usize>>> {
+        match input {
+            Input::Key(Keys::Enter) => {
+                let checked = (0..self.checked.len()).filter(|&index| self.checked[index]);
+                Some(Some(checked.collect()))
+            }
+            Input::Key(Keys::Escape | Keys::Ctrl('c')) => Some(None),
+            Input::Text(text) if text == " " => {
+                if let Some(index) = self.list.current() {
+                    self.checked[index] = !self.checked[index];
+                }
+                None
+            }
+            input => {
+                self.list.input(&input);
+                None
+            }
+        }
+    }
+
+    fn summary(&self, output: &Option<Vec<usize>>) -> String {
+        match output {
+            Some(indexes) => {
+                let items: Vec<&str> = indexes
+                    .iter()
+                    .map(|&index| self.list.items[index].as_str())
+                    .collect();
+                format!("{} {}", self.prompt, items.join(", "))
+            }
+            None => self.prompt.clone(),
+        }
+    }
+}
+
+struct ConfirmWidget {
+    prompt: String,
+    default: Option<bool>,
+}
+
+impl Widget for ConfirmWidget {
+    type Output = bool;
+
+    fn rows(&self, columns: usize) -> Vec<String> {
+        let choices = match self.default {
+            Some(true) => "[Y/n]",
+            Some(false) => "[y/N]",
+            None => "[y/n]",
+        };
+        vec![fit(&format!("{} {} ", self.prompt, choices), columns)]
+    }
+
+    fn input(&mut self, input: Input) -> Option<Option<bool>> {
+        match input {
+            Input::Text(text) if text.eq_ignore_ascii_case("y") => Some(Some(true)),
+            Input::Text(text) if text.eq_ignore_ascii_case("n") => Some(Some(false)),
+            Input::Key(Keys::Enter) => self.default.map(Some),
+            Input::Key(Keys::Escape | Keys::Ctrl('c')) => Some(None),
+            _ => None,
+        }
+    }
+
+    fn summary(&self, output: &Option<bool>) -> String {
+        match output {
+            Some(true) => format!("{} yes", self.prompt),
+            Some(false) => format!("{} no", self.prompt),
+            None => self.prompt.clone(),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+/***************************************************************************************
+ *   query.rs  --  This file is part of k_board.                                       *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+/// Question sent to the terminal, answered through the input stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Query {
+    /// `CSI ? 6 n` (DECXCPR), answered with `CSI ? row ; column R`. The plain
+    /// `CSI 6 n` is not used: its answer `CSI 1 ; 2 R` is also Shift + F3, so a
+    /// key pressed while waiting would be taken as the answer
+    CursorPosition,
+    /// `CSI c`, answered with `CSI ? attributes c`
+    PrimaryDeviceAttributes,
+    /// `CSI > c`, answered with `CSI > type ; version ; rom c`
+    SecondaryDeviceAttributes,
+    /// `CSI ? u`, answered with `CSI ? flags u` by terminals with the kitty keyboard protocol
+    KittyKeyboard,
+    /// `CSI ? mode $ p` (DECRQM), answered with `CSI ? mode ; value $ y`
+    Mode(u16),
+}
+
+/// Private mode of the synchronized output (`CSI ? 2026 h`)
+pub const SYNCHRONIZED_OUTPUT: u16 = 2026;
+
+/// Answer of the terminal to a `Query`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Response {
+    CursorPosition {
+        row: u16,
+        column: u16,
+    },
+    PrimaryDeviceAttributes(Vec<u16>),
+    SecondaryDeviceAttributes(Vec<u16>),
+    KittyKeyboard(u16),
+    /// `value`: 0 not recognized, 1 set, 2 reset, 3 permanently set, 4 permanently reset
+    Mode {
+        mode: u16,
+        value: u16,
+    },
+}
+
+impl Query {
+    /// Bytes written to the terminal
+    pub fn request(&self) -> Vec<u8> {
+        match self {
+            Query::CursorPosition => b"\x1b[?6n".to_vec(),
+            Query::PrimaryDeviceAttributes => b"\x1b[c".to_vec(),
+            Query::SecondaryDeviceAttributes => b"\x1b[>c".to_vec(),
+            Query::KittyKeyboard => b"\x1b[?u".to_vec(),
+            Query::Mode(mode) => format!("\x1b[?{}$p", mode).into_bytes(),
+        }
+    }
+
+    /// Terminals that don't know the query never answer it, so the primary
+    /// device attributes are asked after it: if they come first, there is no answer
+    pub(crate) fn needs_sentinel(&self) -> bool {
+        matches!(
+            self,
+            Query::CursorPosition | Query::KittyKeyboard | Query::Mode(_)
+        )
+    }
+
+    /// True if `response` is the answer to this query
+    pub fn matches(&self, response: &Response) -> bool {
+        match (self, response) {
+            (Query::CursorPosition, Response::CursorPosition { .. }) => true,
+            (Query::PrimaryDeviceAttributes, Response::PrimaryDeviceAttributes(_)) => true,
+            (Query::SecondaryDeviceAttributes, Response::SecondaryDeviceAttributes(_)) => true,
+            (Query::KittyKeyboard, Response::KittyKeyboard(_)) => true,
+            (Query::Mode(mode), Response::Mode { mode: answered, .. }) => mode == answered,
+            _ => false,
+        }
+    }
+}
+
+/// Look for the first terminal response in `bytes`, returning it with
+/// the range of bytes it takes. Everything else (keys) is skipped
+pub(crate) fn find_response(bytes: &[u8]) -> Option<(Response, usize, usize)> {
+    let mut start = 0;
+    while start + 1 < bytes.len() {
+        if bytes[start] == 0x1B && bytes[start + 1] == b'[' {
+            if let Some(end) = csi_end(&bytes[start..]) {
+                if let Some(response) = parse_response(&bytes[start..start + end]) {
+                    return Some((response, start, start + end));
+                }
+                start += end;
+                continue;
+            }
+        }
+        start += 1;
+    }
+    None
+}
+
+/// Length of the `CSI params intermediates final` sequence at the start of `bytes`.
+/// `None` if it's cut, or broken by a byte that can't be in it, like another `ESC`
+fn csi_end(bytes: &[u8]) -> Option<usize> {
+    let position = bytes
+        .iter()
+        .skip(2)
+        .position(|byte| !(0x20..=0x3F).contains(byte))?;
+    (0x40..=0x7E)
+        .contains(&bytes[position + 2])
+        .then_some(position + 3)
+}
+
+/// Parse a whole control sequence as a terminal response
+fn parse_response(sequence: &[u8]) -> Option<Response> {
+    let body = &sequence[2..sequence.len() - 1];
+    let last = *sequence.last()?;
+    let (marker, body) = match body.first() {
+        Some(&marker @ (b'?' | b'>')) => (Some(marker), &body[1..]),
+        _ => (None, body),
+    };
+    let (params, intermediate) = match body.last() {
+        Some(b'$') => (&body[..body.len() - 1], Some(b'$')),
+        _ => (body, None),
+    };
+    let params = parse_params(params)?;
+    match (marker, intermediate, last) {
+        (Some(b'?'), None, b'R') if matches!(params.len(), 2 | 3) => {
+            Some(Response::CursorPosition {
+                row: params[0],
+                column: params[1],
+            })
+        }
+        (Some(b'?'), None, b'c') => Some(Response::PrimaryDeviceAttributes(params)),
+        (Some(b'>'), None, b'c') => Some(Response::SecondaryDeviceAttributes(params)),
+        (Some(b'?'), None, b'u') => Some(Response::KittyKeyboard(*params.first().unwrap_or(&0))),
+        (Some(b'?'), Some(b'$'), b'y') if params.len() == 2 => Some(Response::Mode {
+            mode: params[0],
+            value: params[1],
+        }),
+        _ => None,
+    }
+}
+
+/// Parse `1;2;3` into its numbers, empty parameters are 0
+fn parse_params(bytes: &[u8]) -> Option<Vec<u16>> {
+    if bytes.is_empty() {
+        return Some(Vec::new());
+    }
+    bytes
+        .split(|&byte| byte == b';')
+        .map(|param| match param {
+            [] => Some(0),
+            _ => std::str::from_utf8(param).ok()?.parse().ok(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(bytes: &[u8]) -> Option<Response> {
+        find_response(bytes).map(|(response, _, _)| response)
+    }
+
+    #[test]
+    fn every_response() {
+        let cursor = |row, column| Some(Response::CursorPosition { row, column });
+        assert_eq!(response(b"\x1b[?12;40R"), cursor(12, 40));
+        assert_eq!(response(b"\x1b[?12;40;1R"), cursor(12, 40));
+        assert_eq!(
+            response(b"\x1b[?62;22c"),
+            Some(Response::PrimaryDeviceAttributes(vec![62, 22]))
+        );
+        assert_eq!(
+            response(b"\x1b[>41;390;0c"),
+            Some(Response::SecondaryDeviceAttributes(vec![41, 390, 0]))
+        );
+        assert_eq!(response(b"\x1b[?1u"), Some(Response::KittyKeyboard(1)));
+        assert_eq!(response(b"\x1b[?u"), Some(Response::KittyKeyboard(0)));
+        assert_eq!(
+            response(b"\x1b[?2026;2$y"),
+            Some(Response::Mode {
+                mode: 2026,
+                value: 2
+            })
+        );
+        assert_eq!(
+            response(b"\x1b[?;1$y"),
+            Some(Response::Mode { mode: 0, value: 1 })
+        );
+    }
+
+    #[test]
+    fn matches_its_query() {
+        let mode = Response::Mode {
+            mode: SYNCHRONIZED_OUTPUT,
+            value: 1,
+        };
+        assert!(Query::Mode(SYNCHRONIZED_OUTPUT).matches(&mode));
+        assert!(!Query::Mode(1).matches(&mode));
+        assert!(!Query::KittyKeyboard.matches(&mode));
+        assert!(Query::KittyKeyboard.matches(&Response::KittyKeyboard(0)));
+    }
+
+    #[test]
+    fn response_between_keys() {
+        let bytes = b"a\x1b[A\x1b[?3;7Rb\x1b[?1u";
+        assert_eq!(
+            find_response(bytes),
+            Some((Response::CursorPosition { row: 3, column: 7 }, 4, 11))
+        );
+        assert_eq!(
+            find_response(&bytes[11..]),
+            Some((Response::KittyKeyboard(1), 1, 6))
+        );
+        assert_eq!(find_response(b"abc\x1b[A\x1b[15~\x1b"), None);
+    }
+
+    /// `CSI 1 ; mod R` is F3 with modifiers, not a cursor position
+    #[test]
+    fn keys_are_not_responses() {
+        assert_eq!(find_response(b"\x1b[1;2R"), None);
+        assert_eq!(find_response(b"\x1b[1;5R\x1b[?1;2R"), {
+            let cursor = Response::CursorPosition { row: 1, column: 2 };
+            Some((cursor, 6, 13))
+        });
+        assert_eq!(find_response(b"\x1b[?1;2;3;4R"), None);
+        assert_eq!(find_response(b"\x1b[?x;2R"), None);
+    }
+
+    #[test]
+    fn partial_response() {
+        let whole = b"\x1b[?2026;2$y";
+        for end in 0..whole.len() {
+            assert_eq!(find_response(&whole[..end]), None, "{:?}", &whole[..end]);
+        }
+        let mut bytes = whole[..6].to_vec();
+        bytes.extend_from_slice(b"\x1b[?1u");
+        assert_eq!(
+            find_response(&bytes),
+            Some((Response::KittyKeyboard(1), 6, 11))
+        );
+    }
+}
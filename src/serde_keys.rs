@@ -0,0 +1,224 @@
+/***************************************************************************************
+ *   serde_keys.rs  --  This file is part of k_board.                                  *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::keys::{KeyCode, Keys, Modifiers, RawBytes};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// `Keys` are written with their name (`"Ctrl+C"`, `"Alt+Shift+Left"`), see `Display`
+impl Serialize for Keys {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// `Keys` are read from their name (`"ctrl+c"`, `"C-c"`, see `FromStr`)
+/// or from the structured form written by `structured`
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Form::deserialize(deserializer)? {
+            Form::Name(name) => name.parse().map_err(de::Error::custom),
+            Form::Structured(key) => Ok(key),
+        }
+    }
+}
+
+/// Name or structured form of a key
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Form {
+    Name(String),
+    Structured(#[serde(with = "structured")] Keys),
+}
+
+/// `Modifiers` are written like `"Ctrl+Shift"`, `""` without modifiers
+impl Serialize for Modifiers {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names: Vec<&str> = [
+            (Modifiers::CTRL, "Ctrl"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::SHIFT, "Shift"),
+        ]
+        .iter()
+        .filter(|(modifier, _)| self.contains(*modifier))
+        .map(|&(_, name)| name)
+        .collect();
+        serializer.serialize_str(&names.join("+"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Modifiers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = String::deserialize(deserializer)?;
+        names.split('+').filter(|name| !name.is_empty()).try_fold(
+            Modifiers::NONE,
+            |modifiers, name| match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Ok(modifiers | Modifiers::CTRL),
+                "alt" | "meta" => Ok(modifiers | Modifiers::ALT),
+                "shift" => Ok(modifiers | Modifiers::SHIFT),
+                _ => Err(de::Error::custom(format!("unknown modifier `{}`", name))),
+            },
+        )
+    }
+}
+
+/// `RawBytes` are written as a list of numbers
+impl Serialize for RawBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(RawBytes::new(&Vec::<u8>::deserialize(deserializer)?))
+    }
+}
+
+/// Structured form of `Keys`, with the variant as the tag:
+/// `{"Ctrl": "c"}`, `{"F": 5}`, `"Enter"`, `{"Modified": ["Alt+Shift", "Left"]}`.
+/// Use it with `#[serde(with = "k_board::serde_keys::structured")]`
+pub mod structured {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Keys")]
+    enum KeysDef {
+        Up,
+        Down,
+        Left,
+        Right,
+        Enter,
+        Home,
+        Tab,
+        Backtab,
+        Space,
+        Delete,
+        Escape,
+        End,
+        Char(char),
+        F(u8),
+        Ctrl(char),
+        Alt(char),
+        AltGr(char),
+        Modified(Modifiers, KeyCode),
+        Unknown(RawBytes),
+        Null,
+    }
+
+    pub fn serialize<S: Serializer>(key: &Keys, serializer: S) -> Result<S::Ok, S::Error> {
+        KeysDef::serialize(key, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Keys, D::Error> {
+        KeysDef::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::tests::every_key;
+    use serde_json::{from_str, json, to_string, Value};
+
+    /// `Keys` kept with the structured form
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Structured(#[serde(with = "structured")] Keys);
+
+    #[test]
+    fn name_round_trip() {
+        for key in every_key() {
+            let json = to_string(&key).unwrap();
+            assert_eq!(json, to_string(&key.to_string()).unwrap());
+            assert_eq!(from_str::<Keys>(&json).unwrap(), key, "{}", json);
+        }
+    }
+
+    #[test]
+    fn structured_round_trip() {
+        for key in every_key() {
+            let json = to_string(&Structured(key)).unwrap();
+            assert_eq!(from_str::<Structured>(&json).unwrap(), Structured(key));
+            assert_eq!(from_str::<Keys>(&json).unwrap(), key, "{}", json);
+        }
+    }
+
+    #[test]
+    fn structured_form() {
+        let structured = |key| serde_json::to_value(Structured(key)).unwrap();
+        assert_eq!(structured(Keys::Enter), json!("Enter"));
+        assert_eq!(structured(Keys::Ctrl('c')), json!({"Ctrl": "c"}));
+        assert_eq!(structured(Keys::F(5)), json!({"F": 5}));
+        assert_eq!(
+            structured(Keys::Modified(
+                Modifiers::ALT | Modifiers::SHIFT,
+                KeyCode::Left
+            )),
+            json!({"Modified": ["Alt+Shift", "Left"]})
+        );
+        assert_eq!(
+            structured(Keys::Unknown(RawBytes::new(b"\x1b[1"))),
+            json!({"Unknown": [0x1b, 0x5b, 0x31]})
+        );
+    }
+
+    #[test]
+    fn names_and_aliases() {
+        let key = |value: Value| serde_json::from_value::<Keys>(value).unwrap();
+        assert_eq!(key(json!("ctrl+c")), Keys::Ctrl('c'));
+        assert_eq!(key(json!("C-c")), Keys::Ctrl('c'));
+        assert_eq!(key(json!("<CR>")), Keys::Enter);
+        assert_eq!(key(json!({"Alt": "x"})), Keys::Alt('x'));
+        assert!(serde_json::from_value::<Keys>(json!("ctrl+nothing")).is_err());
+        assert!(serde_json::from_value::<Keys>(json!({"Nothing": 1})).is_err());
+    }
+
+    #[test]
+    fn modifiers_names() {
+        for bits in 0..8 {
+            let modifiers = [Modifiers::SHIFT, Modifiers::ALT, Modifiers::CTRL]
+                .into_iter()
+                .enumerate()
+                .filter(|(bit, _)| bits & (1 << bit) != 0)
+                .fold(Modifiers::NONE, |all, (_, modifier)| all | modifier);
+            let json = to_string(&modifiers).unwrap();
+            assert_eq!(from_str::<Modifiers>(&json).unwrap(), modifiers, "{}", json);
+        }
+        assert_eq!(to_string(&Modifiers::NONE).unwrap(), r#""""#);
+        assert_eq!(
+            to_string(&(Modifiers::SHIFT | Modifiers::CTRL)).unwrap(),
+            r#""Ctrl+Shift""#
+        );
+        assert_eq!(
+            from_str::<Modifiers>(r#""meta+Control""#).unwrap(),
+            Modifiers::ALT | Modifiers::CTRL
+        );
+        assert!(from_str::<Modifiers>(r#""Super""#).is_err());
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let raw = RawBytes::new(b"\x1b[200~");
+        let json = to_string(&raw).unwrap();
+        assert_eq!(json, "[27,91,50,48,48,126]");
+        assert_eq!(from_str::<RawBytes>(&json).unwrap(), raw);
+        let long = from_str::<RawBytes>(&to_string(&[7u8; 20]).unwrap()).unwrap();
+        assert_eq!(long.as_bytes(), [7; crate::keys::RAW_BYTES]);
+    }
+}
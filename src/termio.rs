@@ -19,9 +19,13 @@
  **************************************************************************************/
 
 use std::{
-    ffi::{c_int, c_uint},
-    io::{stdout, Error, Result, Write},
+    ffi::{c_int, c_short, c_uint, c_ulong, c_ushort, c_void},
+    fs::{File, OpenOptions},
+    io::{stdout, Error, ErrorKind, Result, Write},
     mem::zeroed,
+    os::unix::io::AsRawFd,
+    panic,
+    sync::{Mutex, Once, PoisonError},
 };
 
 #[cfg(any(
@@ -46,8 +50,38 @@ type sighandler_t = size_t;
 ))]
 pub const SIGINT: c_int = 2;
 
+/// There is data to read, for `poll()`
+pub const POLLIN: c_short = 0x001;
+/// Error condition, for `poll()`
+pub const POLLERR: c_short = 0x008;
+/// Hang up, for `poll()`
+pub const POLLHUP: c_short = 0x010;
+/// Invalid fd, for `poll()`
+pub const POLLNVAL: c_short = 0x020;
+
+/// Shared lock of `flock()`
+pub const LOCK_SH: c_int = 1;
+/// Exclusive lock of `flock()`
+pub const LOCK_EX: c_int = 2;
+
+/// Get the file status flags, for `fcntl()`
+pub const F_GETFL: c_int = 3;
+/// Set the file status flags, for `fcntl()`
+pub const F_SETFL: c_int = 4;
+/// Reads & writes return `EAGAIN` instead of waiting
+pub const O_NONBLOCK: c_int = 0o4000;
+/// Close the fd on `exec()`, for `pipe2()`
+pub const O_CLOEXEC: c_int = 0o2000000;
+
+/// `CSI > 4 ; 2 m`, xterm `modifyOtherKeys` level 2
+pub(crate) const MODIFY_OTHER_KEYS_ON: &[u8] = b"\x1b[>4;2m";
+/// `CSI > 4 ; 0 m`, xterm `modifyOtherKeys` off
+pub(crate) const MODIFY_OTHER_KEYS_OFF: &[u8] = b"\x1b[>4;0m";
+
 const TCSANOW: c_int = 0;
+const TIOCGWINSZ: c_ulong = 0x5413;
 const TCSADRAIN: c_int = 0;
+const ISIG: tcflag_t = 0x00000001;
 const ICANON: tcflag_t = 0x00000002;
 const ECHO: tcflag_t = 0x00000008;
 const IXON: tcflag_t = 0x00000400;
@@ -55,30 +89,200 @@ const IXANY: tcflag_t = 0x00000800;
 
 /// Setup the raw mode in the console to take the termios struct
 pub fn setup_raw_mode() -> Result<termios> {
+    setup_raw_mode_fd(TCSANOW, true)
+}
+
+/// Setup the raw mode in the terminal open in `fd`, like `/dev/tty`. Without
+/// `signals`, Ctrl + C & Ctrl + Z arrive as bytes instead of stopping the program
+pub fn setup_raw_mode_fd(fd: c_int, signals: bool) -> Result<termios> {
     let mut termios: termios = unsafe { zeroed() };
-    if unsafe { tcgetattr(TCSANOW, &mut termios) } < 0 {
+    if unsafe { tcgetattr(fd, &mut termios) } < 0 {
         return Err(Error::last_os_error());
     }
     let original_termios = termios.clone();
     termios.c_lflag &= !(ICANON | ECHO);
+    if !signals {
+        termios.c_lflag &= !ISIG;
+    }
     termios.c_iflag &= !(IXON | 0x00001000 | IXANY);
     termios.c_cc[6] = 0;
     termios.c_cc[5] = 1;
-    if unsafe { tcsetattr(TCSANOW, TCSADRAIN, &termios) } < 0 {
+    if unsafe { tcsetattr(fd, TCSADRAIN, &termios) } < 0 {
         return Err(Error::last_os_error());
     }
     Ok(original_termios)
 }
 
+/// Terminal modes set along the raw mode by `setup_raw_mode_with`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TerminalMode {
+    /// Alternate screen buffer (`CSI ? 1049 h`)
+    AlternateScreen,
+    /// Cursor hidden (`CSI ? 25 l`)
+    HideCursor,
+    /// Application cursor keys, DECCKM (`CSI ? 1 h`): arrows send `ESC O A`..
+    ApplicationCursorKeys,
+    /// Mouse clicks & drags reported in SGR form (`CSI ? 1000 ; 1002 ; 1006 h`)
+    Mouse,
+    /// Bracketed paste (`CSI ? 2004 h`)
+    BracketedPaste,
+    /// Focus in & out reports (`CSI ? 1004 h`)
+    FocusEvents,
+}
+
+impl TerminalMode {
+    fn enable(self) -> &'static [u8] {
+        match self {
+            TerminalMode::AlternateScreen => b"\x1b[?1049h",
+            TerminalMode::HideCursor => b"\x1b[?25l",
+            TerminalMode::ApplicationCursorKeys => b"\x1b[?1h",
+            TerminalMode::Mouse => b"\x1b[?1000h\x1b[?1002h\x1b[?1006h",
+            TerminalMode::BracketedPaste => b"\x1b[?2004h",
+            TerminalMode::FocusEvents => b"\x1b[?1004h",
+        }
+    }
+
+    fn disable(self) -> &'static [u8] {
+        match self {
+            TerminalMode::AlternateScreen => b"\x1b[?1049l",
+            TerminalMode::HideCursor => b"\x1b[?25h",
+            TerminalMode::ApplicationCursorKeys => b"\x1b[?1l",
+            TerminalMode::Mouse => b"\x1b[?1006l\x1b[?1002l\x1b[?1000l",
+            TerminalMode::BracketedPaste => b"\x1b[?2004l",
+            TerminalMode::FocusEvents => b"\x1b[?1004l",
+        }
+    }
+}
+
+/// Raw mode sessions alive, oldest first, so the panic hook can undo them
+static SESSIONS: Mutex<Vec<(c_int, termios, Vec<TerminalMode>)>> = Mutex::new(Vec::new());
+static PANIC_HOOK: Once = Once::new();
+
+/// Raw mode with terminal modes, undone when dropped
+pub struct RawMode {
+    /// Sessions alive when this one started
+    depth: usize,
+    /// Terminal opened for the session, kept open until it's restored
+    _tty: Option<File>,
+}
+
+/// Setup the raw mode on `/dev/tty` & turn on `modes` in order until the
+/// `RawMode` is dropped, so stdin & stdout can be redirected. Then the modes
+/// are turned off in reverse order & the termios is restored.
+/// A panic in any thread restores all the sessions alive, as the terminal is
+/// shared, before the panic message is printed
+pub fn setup_raw_mode_with(modes: &[TerminalMode]) -> Result<RawMode> {
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let mut raw_mode = raw_mode_fd(tty.as_raw_fd(), true, modes)?;
+    raw_mode._tty = Some(tty);
+    Ok(raw_mode)
+}
+
+/// `setup_raw_mode_with` on the terminal open in `fd`, see `setup_raw_mode_fd`.
+/// The modes are written to `fd`, that must be open for writing
+pub(crate) fn raw_mode_fd(fd: c_int, signals: bool, modes: &[TerminalMode]) -> Result<RawMode> {
+    let termios_enviroment: termios = setup_raw_mode_fd(fd, signals)?;
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_sessions(0);
+            previous(info);
+        }));
+    });
+    let mut sessions = SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+    let raw_mode = RawMode {
+        depth: sessions.len(),
+        _tty: None,
+    };
+    sessions.push((fd, termios_enviroment, modes.to_vec()));
+    drop(sessions);
+    stdout().flush()?;
+    for mode in modes {
+        write_all_fd(fd, mode.enable())?;
+    }
+    Ok(raw_mode)
+}
+
+/// Write all of `bytes` to `fd`, the terminal that the modes belong to
+fn write_all_fd(fd: c_int, mut bytes: &[u8]) -> Result<()> {
+    while !bytes.is_empty() {
+        let written = unsafe { write(fd, bytes.as_ptr().cast(), bytes.len()) };
+        if written < 0 {
+            let error = Error::last_os_error();
+            if error.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        bytes = &bytes[written as usize..];
+    }
+    Ok(())
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        restore_sessions(self.depth);
+    }
+}
+
+/// Undo the newest sessions down to `depth`
+fn restore_sessions(depth: usize) {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+    while sessions.len() > depth {
+        let Some((fd, termios_enviroment, modes)) = sessions.pop() else {
+            break;
+        };
+        let _ = stdout().flush();
+        for mode in modes.iter().rev() {
+            let _ = write_all_fd(fd, mode.disable());
+        }
+        let _ = restore_fd(fd, &termios_enviroment);
+    }
+}
+
 /// Restore the termios enviroment
 pub fn restore(termios_enviroment: &termios) -> Result<()> {
     stdout().flush().unwrap();
-    if unsafe { tcsetattr(TCSANOW, TCSADRAIN, termios_enviroment) } < 0 {
+    restore_fd(TCSANOW, termios_enviroment)
+}
+
+/// Restore the termios enviroment of the terminal open in `fd`
+pub fn restore_fd(fd: c_int, termios_enviroment: &termios) -> Result<()> {
+    if unsafe { tcsetattr(fd, TCSADRAIN, termios_enviroment) } < 0 {
         return Err(Error::last_os_error());
     }
     Ok(())
 }
 
+/// Size of the terminal as (columns, rows)
+pub fn terminal_size() -> Result<(u16, u16)> {
+    terminal_size_fd(1)
+}
+
+/// Size of the terminal open in `fd` as (columns, rows)
+pub fn terminal_size_fd(fd: c_int) -> Result<(u16, u16)> {
+    let mut size: winsize = unsafe { zeroed() };
+    if unsafe { ioctl(fd, TIOCGWINSZ, &mut size) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok((size.ws_col, size.ws_row))
+}
+
+/// Ask the terminal for xterm `modifyOtherKeys` level 2 (`CSI > 4 ; 2 m`),
+/// then the Ctrl, Alt & Shift combinations arrive as `CSI 27 ; mod ; code ~`
+pub fn enable_modify_other_keys() -> Result<()> {
+    let mut stdout = stdout();
+    stdout.write_all(MODIFY_OTHER_KEYS_ON)?;
+    stdout.flush()
+}
+
+/// Turn off xterm `modifyOtherKeys` (`CSI > 4 ; 0 m`)
+pub fn disable_modify_other_keys() -> Result<()> {
+    let mut stdout = stdout();
+    stdout.write_all(MODIFY_OTHER_KEYS_OFF)?;
+    stdout.flush()
+}
+
 /// Modify `CTRL_C` -> true, then the iterator get the change &
 /// return the `Keys::Ctrl('c')` || `Keys::Ctrl('C')`
 #[cfg(any(
@@ -105,6 +309,24 @@ pub struct termios {
     c_ospeed: u32,
 }
 
+/// look more at <https://man7.org/linux/man-pages/man2/TIOCGWINSZ.2const.html>
+#[repr(C)]
+pub struct winsize {
+    pub ws_row: c_ushort,
+    pub ws_col: c_ushort,
+    pub ws_xpixel: c_ushort,
+    pub ws_ypixel: c_ushort,
+}
+
+/// look more at <https://man7.org/linux/man-pages/man2/poll.2.html>
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
 #[link(name = "c")]
 extern "C" {
     /// `tcsetattr()` sets the parameters associated with the terminal
@@ -121,4 +343,25 @@ extern "C" {
     /// which is either SIG_IGN, SIG_DFL, or the address of a programmer-
     /// defined function (a "signal handler").
     pub fn signal(signum: c_int, handler: sighandler_t) -> sighandler_t;
+    /// `ioctl()` manipulates the underlying device parameters of special files,
+    /// like the size of the terminal with `TIOCGWINSZ`
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    /// `flock()` applies or removes an advisory lock on the open file specified
+    /// by fd, the lock is released when the file is closed
+    pub fn flock(fd: c_int, operation: c_int) -> c_int;
+    /// `poll()` waits for one of a set of file descriptors to become ready
+    /// to perform I/O, or for `timeout` milliseconds (-1 waits forever)
+    pub fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+    /// `pipe2()` creates a unidirectional data channel, `fds[0]` is the
+    /// read end & `fds[1]` the write end, with the `O_*` flags set on both
+    pub fn pipe2(fds: *mut c_int, flags: c_int) -> c_int;
+    /// `read()` reads up to count bytes from the file descriptor fd into buf
+    pub fn read(fd: c_int, buf: *mut c_void, count: size_t) -> isize;
+    /// `write()` writes up to count bytes from buf to the file descriptor fd
+    pub fn write(fd: c_int, buf: *const c_void, count: size_t) -> isize;
+    /// `close()` closes a file descriptor
+    pub fn close(fd: c_int) -> c_int;
+    /// `fcntl()` performs the operation cmd on the file descriptor fd,
+    /// like getting & setting its status flags
+    pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
 }
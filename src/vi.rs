@@ -0,0 +1,500 @@
+/***************************************************************************************
+ *   vi.rs  --  This file is part of k_board.                                          *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    keys::Keys,
+    line_editor::{Input, Line},
+};
+
+/// Highest count taken, so a long run of digits can't overflow or make `p`
+/// paste without end. Motions are also limited by the length of the line
+const MAX_COUNT: usize = 9999;
+
+/// What the line editor does after a key in normal mode
+pub(crate) enum Action {
+    None,
+    Submit,
+    Cancel,
+    Older,
+    Newer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    WordForward,
+    WordBack,
+    WordEnd,
+    Start,
+    End,
+    /// The whole line, for `dd` & `cc`
+    Line,
+}
+
+/// Where `i`, `a`, `I` & `A` start inserting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Insert {
+    Before,
+    After,
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Move(Motion),
+    Delete(Motion),
+    Change(Motion),
+    Put { after: bool },
+    Insert(Insert),
+}
+
+/// Last change, repeated by `.`, with the text typed after it
+#[derive(Debug, Clone)]
+struct Change {
+    command: Command,
+    count: usize,
+    text: String,
+}
+
+/// Vi mode state of the line being read, it starts in insert mode
+#[derive(Debug, Default)]
+pub(crate) struct Vi {
+    pub(crate) normal: bool,
+    count: Option<usize>,
+    /// `d` or `c` waiting for its motion, with the count typed before it
+    operator: Option<(char, Option<usize>)>,
+    last_change: Option<Change>,
+    /// Change whose text is being typed in insert mode
+    inserting: Option<Change>,
+    undo: Vec<Line>,
+}
+
+impl Vi {
+    /// Esc in insert mode, back to normal mode over the last char typed
+    pub(crate) fn escape(&mut self, line: &mut Line) {
+        self.normal = true;
+        if let Some(change) = self.inserting.take() {
+            self.last_change = Some(change);
+        }
+        line.left();
+    }
+
+    /// Text typed in insert mode, kept for `.`
+    pub(crate) fn typed(&mut self, text: &str) {
+        if let Some(change) = &mut self.inserting {
+            change.text.push_str(text);
+        }
+    }
+
+    /// Backspace in insert mode
+    pub(crate) fn erased(&mut self) {
+        if let Some(change) = &mut self.inserting {
+            change.text.pop();
+        }
+    }
+
+    /// Keep the cursor over a char, as normal mode does
+    pub(crate) fn clamp(line: &mut Line) {
+        line.cursor = line.cursor.min(line.chars.len().saturating_sub(1));
+    }
+
+    /// Key in normal mode: counts, motions (h l w b e 0 $), operators (d c)
+    /// followed by a motion, x D C p P i a I A u . & j k for the history
+    pub(crate) fn normal(
+        &mut self,
+        line: &mut Line,
+        kill_buffer: &mut String,
+        input: Input,
+    ) -> Action {
+        let c = match input {
+            Input::Text(text) => {
+                for (index, c) in text.char_indices() {
+                    match self.command(line, kill_buffer, c) {
+                        Action::None if self.normal => {}
+                        Action::None => {
+                            let typed = &text[index + c.len_utf8()..];
+                            line.insert(typed);
+                            self.typed(typed);
+                            return Action::None;
+                        }
+                        action => return action,
+                    }
+                }
+                return Action::None;
+            }
+            Input::Key(Keys::Enter) => return Action::Submit,
+            Input::Key(Keys::Ctrl('c')) => return Action::Cancel,
+            Input::Key(Keys::Left | Keys::Delete | Keys::Ctrl('h')) => 'h',
+            Input::Key(Keys::Right) => 'l',
+            Input::Key(Keys::Home) => '0',
+            Input::Key(Keys::End) => '$',
+            Input::Key(Keys::Up) => 'k',
+            Input::Key(Keys::Down) => 'j',
+            Input::Key(Keys::Escape) => {
+                self.count = None;
+                self.operator = None;
+                return Action::None;
+            }
+            Input::Key(_) => return Action::None,
+        };
+        self.command(line, kill_buffer, c)
+    }
+
+    fn command(&mut self, line: &mut Line, kill_buffer: &mut String, c: char) -> Action {
+        if let Some(digit) = c
+            .to_digit(10)
+            .filter(|&digit| digit > 0 || self.count.is_some())
+        {
+            let count = self.count.unwrap_or(0).saturating_mul(10);
+            self.count = Some(count.saturating_add(digit as usize).min(MAX_COUNT));
+            return Action::None;
+        }
+        let count = self.count.take();
+        if let Some((operator, first)) = self.operator.take() {
+            let motion = match c == operator {
+                true => Some(Motion::Line),
+                false => motion(c),
+            };
+            if let Some(motion) = motion {
+                let count = first.unwrap_or(1).saturating_mul(count.unwrap_or(1));
+                match operator {
+                    'd' => self.run(line, kill_buffer, Command::Delete(motion), count),
+                    _ => self.run(line, kill_buffer, Command::Change(motion), count),
+                }
+            }
+            return Action::None;
+        }
+        let command = match c {
+            'd' | 'c' => {
+                self.operator = Some((c, count));
+                return Action::None;
+            }
+            'x' => Command::Delete(Motion::Right),
+            'D' => Command::Delete(Motion::End),
+            'C' => Command::Change(Motion::End),
+            'p' => Command::Put { after: true },
+            'P' => Command::Put { after: false },
+            'i' => Command::Insert(Insert::Before),
+            'a' => Command::Insert(Insert::After),
+            'I' => Command::Insert(Insert::Start),
+            'A' => Command::Insert(Insert::End),
+            'k' => return Action::Older,
+            'j' => return Action::Newer,
+            'u' => {
+                if let Some(previous) = self.undo.pop() {
+                    *line = previous;
+                    Vi::clamp(line);
+                }
+                return Action::None;
+            }
+            '.' => {
+                self.repeat(line, kill_buffer, count);
+                return Action::None;
+            }
+            c => match motion(c) {
+                Some(motion) => Command::Move(motion),
+                None => return Action::None,
+            },
+        };
+        self.run(line, kill_buffer, command, count.unwrap_or(1));
+        Action::None
+    }
+
+    /// `.`: run the last change again, with `count` if given
+    fn repeat(&mut self, line: &mut Line, kill_buffer: &mut String, count: Option<usize>) {
+        let Some(change) = self.last_change.clone() else {
+            return;
+        };
+        self.run(
+            line,
+            kill_buffer,
+            change.command,
+            count.unwrap_or(change.count),
+        );
+        if !self.normal {
+            line.insert(&change.text);
+            self.typed(&change.text);
+            self.escape(line);
+        }
+    }
+
+    fn run(&mut self, line: &mut Line, kill_buffer: &mut String, command: Command, count: usize) {
+        let count = count.min(MAX_COUNT);
+        if !matches!(command, Command::Move(_)) {
+            self.undo.push(line.clone());
+            self.last_change = Some(Change {
+                command,
+                count,
+                text: String::new(),
+            });
+        }
+        match command {
+            Command::Move(motion) => line.cursor = target(&line.chars, line.cursor, motion, count),
+            Command::Delete(motion) => {
+                let (start, end) = range(line, motion, count, false);
+                *kill_buffer = line.kill(start, end);
+            }
+            Command::Change(motion) => {
+                let (start, end) = range(line, motion, count, true);
+                *kill_buffer = line.kill(start, end);
+            }
+            Command::Put { after } => {
+                if after {
+                    line.right();
+                }
+                line.insert(&kill_buffer.repeat(count));
+                line.left();
+            }
+            Command::Insert(at) => {
+                line.cursor = match at {
+                    Insert::Before => line.cursor,
+                    Insert::After => (line.cursor + 1).min(line.chars.len()),
+                    Insert::Start => 0,
+                    Insert::End => line.chars.len(),
+                }
+            }
+        }
+        if let Command::Change(_) | Command::Insert(_) = command {
+            self.normal = false;
+            self.inserting = self.last_change.take();
+        } else {
+            Vi::clamp(line);
+        }
+    }
+}
+
+fn motion(c: char) -> Option<Motion> {
+    match c {
+        'h' => Some(Motion::Left),
+        'l' | ' ' => Some(Motion::Right),
+        'w' => Some(Motion::WordForward),
+        'b' => Some(Motion::WordBack),
+        'e' => Some(Motion::WordEnd),
+        '0' | '^' => Some(Motion::Start),
+        '$' => Some(Motion::End),
+        _ => None,
+    }
+}
+
+/// Cursor after moving `count` times. Each move goes at least one char or
+/// stays, so more moves than chars don't change it
+fn target(chars: &[char], cursor: usize, motion: Motion, count: usize) -> usize {
+    (0..count.min(chars.len() + 1)).fold(cursor, |cursor, _| match motion {
+        Motion::Left => cursor.saturating_sub(1),
+        Motion::Right => (cursor + 1).min(chars.len()),
+        Motion::WordForward => word_forward(chars, cursor),
+        Motion::WordBack => word_back(chars, cursor),
+        Motion::WordEnd => word_end(chars, cursor + 1),
+        Motion::Start => 0,
+        Motion::End | Motion::Line => chars.len(),
+    })
+}
+
+/// Chars taken by an operator. `e` takes the last char of the word & `cw`
+/// works as `ce` when the cursor is on a word
+fn range(line: &Line, motion: Motion, count: usize, change: bool) -> (usize, usize) {
+    let (chars, cursor) = (&line.chars, line.cursor);
+    let on_word = chars.get(cursor).is_some_and(|&c| class(c) != 0);
+    match motion {
+        Motion::Line => (0, chars.len()),
+        Motion::WordForward if change && on_word => {
+            let count = count.min(chars.len());
+            let end = (1..count).fold(word_end(chars, cursor), |end, _| word_end(chars, end + 1));
+            (cursor, (end + 1).min(chars.len()))
+        }
+        Motion::WordEnd => {
+            let end = target(chars, cursor, motion, count);
+            (cursor, (end + 1).min(chars.len()))
+        }
+        _ => {
+            let target = target(chars, cursor, motion, count);
+            (cursor.min(target), cursor.max(target))
+        }
+    }
+}
+
+/// 0 for whitespace, 1 for word chars & 2 for punctuation
+fn class(c: char) -> u8 {
+    match c {
+        c if c.is_whitespace() => 0,
+        c if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    }
+}
+
+/// Start of the next word
+fn word_forward(chars: &[char], mut pos: usize) -> usize {
+    if let Some(&c) = chars.get(pos) {
+        let start = class(c);
+        while pos < chars.len() && start != 0 && class(chars[pos]) == start {
+            pos += 1;
+        }
+    }
+    while pos < chars.len() && class(chars[pos]) == 0 {
+        pos += 1;
+    }
+    pos
+}
+
+/// Start of the word before `pos`
+fn word_back(chars: &[char], mut pos: usize) -> usize {
+    while pos > 0 && class(chars[pos - 1]) == 0 {
+        pos -= 1;
+    }
+    if let Some(&c) = pos.checked_sub(1).map(|pos| &chars[pos]) {
+        let start = class(c);
+        while pos > 0 && class(chars[pos - 1]) == start {
+            pos -= 1;
+        }
+    }
+    pos
+}
+
+/// Last char of the word at or after `pos`
+fn word_end(chars: &[char], mut pos: usize) -> usize {
+    while pos < chars.len() && class(chars[pos]) == 0 {
+        pos += 1;
+    }
+    if pos >= chars.len() {
+        return chars.len().saturating_sub(1);
+    }
+    let start = class(chars[pos]);
+    while pos + 1 < chars.len() && class(chars[pos + 1]) == start {
+        pos += 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Line in normal mode with the cursor at the start
+    fn normal(text: &str) -> (Vi, Line, String) {
+        let vi = Vi {
+            normal: true,
+            ..Vi::default()
+        };
+        let line = Line {
+            cursor: 0,
+            ..Line::from(text)
+        };
+        (vi, line, String::new())
+    }
+
+    /// Type `keys` as the line editor does, with `\x1b` for Esc
+    fn press(state: &mut (Vi, Line, String), keys: &str) {
+        let (vi, line, kill_buffer) = state;
+        for c in keys.chars() {
+            if c == '\x1b' {
+                match vi.normal {
+                    true => vi.normal(line, kill_buffer, Input::Key(Keys::Escape)),
+                    false => {
+                        vi.escape(line);
+                        Action::None
+                    }
+                };
+            } else if vi.normal {
+                vi.normal(line, kill_buffer, Input::Text(c.to_string()));
+            } else {
+                line.insert(&c.to_string());
+                vi.typed(&c.to_string());
+            }
+        }
+    }
+
+    fn cursor_after(keys: &str) -> usize {
+        let mut state = normal("foo bar baz");
+        press(&mut state, keys);
+        state.1.cursor
+    }
+
+    fn text_after(keys: &str) -> String {
+        let mut state = normal("foo bar baz");
+        press(&mut state, keys);
+        state.1.text()
+    }
+
+    #[test]
+    fn motions() {
+        assert_eq!(cursor_after("l"), 1);
+        assert_eq!(cursor_after("llh"), 1);
+        assert_eq!(cursor_after("h"), 0);
+        assert_eq!(cursor_after("w"), 4);
+        assert_eq!(cursor_after("e"), 2);
+        assert_eq!(cursor_after("ee"), 6);
+        assert_eq!(cursor_after("$b"), 8);
+        assert_eq!(cursor_after("$bb"), 4);
+        assert_eq!(cursor_after("$"), 10);
+        assert_eq!(cursor_after("$0"), 0);
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(cursor_after("2w"), 8);
+        assert_eq!(cursor_after("3l"), 3);
+        assert_eq!(cursor_after("2e"), 6);
+        assert_eq!(cursor_after("10l"), 10);
+        assert_eq!(text_after("3x"), " bar baz");
+        assert_eq!(text_after("d2w"), "baz");
+        assert_eq!(text_after("2dw"), "baz");
+        assert_eq!(text_after("2d2w"), "");
+    }
+
+    #[test]
+    fn delete_and_change_words() {
+        let mut state = normal("foo bar baz");
+        press(&mut state, "dw");
+        assert_eq!(
+            (state.1.text(), state.2.as_str()),
+            (String::from("bar baz"), "foo ")
+        );
+        assert_eq!(text_after("cwqux\x1b"), "qux bar baz");
+        assert_eq!(text_after("wcwqux\x1b"), "foo qux baz");
+        assert_eq!(text_after("c2wqux\x1b"), "qux baz");
+        assert_eq!(text_after("wD"), "foo ");
+        assert_eq!(text_after("dd"), "");
+    }
+
+    #[test]
+    fn repeat_and_undo() {
+        assert_eq!(text_after("dw."), "baz");
+        assert_eq!(text_after("dw2."), "");
+        assert_eq!(text_after("cwqux\x1bw."), "qux qux baz");
+        assert_eq!(text_after("dwu"), "foo bar baz");
+        assert_eq!(text_after("dwdwuu"), "foo bar baz");
+        assert_eq!(text_after("dwdwu"), "bar baz");
+        assert_eq!(text_after("cwqux\x1bu"), "foo bar baz");
+    }
+
+    #[test]
+    fn huge_counts() {
+        let digits = "9".repeat(40);
+        assert_eq!(cursor_after(&format!("{}l", digits)), 10);
+        assert_eq!(cursor_after(&format!("{}w", digits)), 10);
+        assert_eq!(text_after(&format!("{0}d{0}w", digits)), "");
+        assert_eq!(text_after(&format!("c{}wqux\x1b", digits)), "qux");
+        let mut state = normal("ab");
+        press(&mut state, &format!("x{}p", digits));
+        assert_eq!(state.1.chars.len(), 1 + MAX_COUNT);
+    }
+}
@@ -0,0 +1,123 @@
+/***************************************************************************************
+ *   wait.rs  --  This file is part of k_board.                                        *
+ *                                                                                     *
+ *   Copyright (C) 2024 Mateo Lafalce                                                  *
+ *                                                                                     *
+ *   k_board is free software: you can redistribute it and/or modify                   *
+ *   it under the terms of the GNU General Public License as published                 *
+ *   by the Free Software Foundation, either version 3 of the License,                 *
+ *   or (at your option) any later version.                                            *
+ *                                                                                     *
+ *   k_board is distributed in the hope that it will be useful,                        *
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty                       *
+ *   of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.                           *
+ *   See the GNU General Public License for more details.                              *
+ *                                                                                     *
+ *   You should have received a copy of the GNU General Public License                 *
+ *   along with this program.  If not, see http://www.gnu.org/licenses/.               *
+ *                                                                                     *
+ **************************************************************************************/
+
+use crate::{
+    event::KeyEvent,
+    keyboard::Keyboard,
+    keys::Keys,
+    termio::{poll, pollfd, restore_fd, setup_raw_mode_fd, termios, POLLIN},
+};
+use std::{
+    ffi::{c_int, c_ulong},
+    io::{Error, ErrorKind, Result},
+    time::{Duration, Instant},
+};
+
+/// What `Keyboard::wait` woke up for
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ready {
+    /// A key event, never `Keys::Null`
+    Key(KeyEvent),
+    /// One of the extra fds is readable, or hung up
+    Fd(c_int),
+    /// The timeout passed first
+    Timeout,
+}
+
+impl Keyboard {
+    /// Wait for a key or for one of `fds` (a timerfd, a socket, an eventfd..)
+    /// to be readable, up to `timeout` (forever if `None`). Keys already read
+    /// come first, then the fds in the order given, so an fd left unread keeps
+    /// waking it up. The terminal is in raw mode only while waiting & Ctrl + C
+    /// arrives as a key. The end of the input is an `UnexpectedEof` error
+    pub fn wait(&mut self, fds: &[c_int], timeout: Option<Duration>) -> Result<Ready> {
+        if let Some(event) = self.pending_key() {
+            return Ok(Ready::Key(event));
+        }
+        let fd = self.fd();
+        let termios_enviroment: termios = setup_raw_mode_fd(fd, false)?;
+        let ready = self
+            .set_modify_other_keys(true)
+            .and_then(|_| self.poll_fds(fds, timeout));
+        let _ = self.set_modify_other_keys(false);
+        restore_fd(fd, &termios_enviroment)?;
+        ready
+    }
+
+    /// Next key event in the bytes already read, skipping `Keys::Null`
+    fn pending_key(&mut self) -> Option<KeyEvent> {
+        let mut event = self.pending_event()?;
+        while event.key == Keys::Null {
+            event = self.pending_event()?;
+        }
+        Some(event)
+    }
+
+    /// Poll the terminal & `fds` until a key is decoded, an fd is ready
+    /// or the deadline passes
+    fn poll_fds(&mut self, fds: &[c_int], timeout: Option<Duration>) -> Result<Ready> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut poll_fds: Vec<pollfd> = [self.fd()]
+            .iter()
+            .chain(fds)
+            .map(|&fd| pollfd {
+                fd,
+                events: POLLIN,
+                revents: 0,
+            })
+            .collect();
+        loop {
+            let millis = remaining(deadline);
+            let polled = unsafe { poll(poll_fds.as_mut_ptr(), poll_fds.len() as c_ulong, millis) };
+            if polled < 0 {
+                let err = Error::last_os_error();
+                if err.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if poll_fds[0].revents & POLLIN != 0 {
+                if self.fill()? == 0 {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+                if let Some(event) = self.pending_key() {
+                    return Ok(Ready::Key(event));
+                }
+            } else if poll_fds[0].revents != 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            if let Some(ready) = poll_fds[1..].iter().find(|fd| fd.revents != 0) {
+                return Ok(Ready::Fd(ready.fd));
+            }
+            if polled == 0 {
+                return Ok(Ready::Timeout);
+            }
+        }
+    }
+}
+
+/// Milliseconds left for `poll()`, rounded up, -1 without a deadline
+fn remaining(deadline: Option<Instant>) -> c_int {
+    let Some(deadline) = deadline else {
+        return -1;
+    };
+    let left = deadline.saturating_duration_since(Instant::now());
+    left.as_nanos().div_ceil(1_000_000).min(c_int::MAX as u128) as c_int
+}
@@ -0,0 +1,82 @@
+//! `keys.events` is a recording of a 64-bit little endian `struct input_event`
+//! stream, with the `EV_MSC` scancodes & `EV_SYN` reports between the keys:
+//! a (down, repeat, up), Shift+a, Ctrl+c & RightAlt+e
+#![cfg(all(
+    feature = "evdev",
+    target_pointer_width = "64",
+    target_endian = "little"
+))]
+
+use k_board::{
+    evdev::{Device, DeviceEvent, KeyState},
+    keys::{Keys, Modifiers},
+};
+use std::{fs::File, io::Result};
+
+const KEY_E: u16 = 18;
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_A: u16 = 30;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_C: u16 = 46;
+const KEY_RIGHTALT: u16 = 100;
+
+fn recording() -> Result<Device> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/keys.events");
+    Ok(Device::from_reader(File::open(path)?))
+}
+
+fn keys(events: &[DeviceEvent]) -> Vec<(Option<Keys>, u16, KeyState)> {
+    events
+        .iter()
+        .map(|event| (event.key, event.code, event.state))
+        .collect()
+}
+
+#[test]
+fn recorded_keys() -> Result<()> {
+    let events = recording()?.collect::<Result<Vec<_>>>()?;
+    use KeyState::{Down, Repeat, Up};
+    assert_eq!(
+        keys(&events),
+        [
+            (Some(Keys::Char('a')), KEY_A, Down),
+            (Some(Keys::Char('a')), KEY_A, Repeat),
+            (Some(Keys::Char('a')), KEY_A, Up),
+            (None, KEY_LEFTSHIFT, Down),
+            (Some(Keys::Char('A')), KEY_A, Down),
+            (Some(Keys::Char('A')), KEY_A, Up),
+            (None, KEY_LEFTSHIFT, Up),
+            (None, KEY_LEFTCTRL, Down),
+            (Some(Keys::Ctrl('c')), KEY_C, Down),
+            (Some(Keys::Ctrl('c')), KEY_C, Up),
+            (None, KEY_LEFTCTRL, Up),
+            (None, KEY_RIGHTALT, Down),
+            (Some(Keys::AltGr('e')), KEY_E, Down),
+            (Some(Keys::AltGr('e')), KEY_E, Up),
+            (None, KEY_RIGHTALT, Up),
+        ]
+    );
+    assert!(events.windows(2).all(|pair| pair[0].time < pair[1].time));
+    Ok(())
+}
+
+#[test]
+fn recorded_modifiers() -> Result<()> {
+    let mut device = recording()?;
+    let mut held = Vec::new();
+    while let Some(event) = device.next_key()? {
+        if event.state == KeyState::Down && event.key.is_some() {
+            held.push(device.modifiers());
+        }
+    }
+    assert_eq!(
+        held,
+        [
+            Modifiers::NONE,
+            Modifiers::SHIFT,
+            Modifiers::CTRL,
+            Modifiers::ALT
+        ]
+    );
+    Ok(())
+}